@@ -0,0 +1,13 @@
+// Part of ethercat-rs. Copyright 2018-2020 by the authors.
+// This work is dual-licensed under Apache 2.0 and MIT terms.
+
+use std::env;
+
+fn main() {
+    println!("cargo:rustc-check-cfg=cfg(ref_clk_time_64)");
+    // Set by the ethercat-sys build script if the master header defines
+    // the 64-bit reference clock ioctls.
+    if env::var_os("DEP_ETHERCAT_REF_CLK_TIME_64").is_some() {
+        println!("cargo:rustc-cfg=ref_clk_time_64");
+    }
+}
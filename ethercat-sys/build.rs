@@ -31,9 +31,10 @@ fn main() {
             .expect("Couldn't write bindings!");
 
         // Generate the EC_IOCTL_ ioctl numbers -- bindgen can't handle them.
-        let code = fs::read_to_string(&format!("{}/master/ioctl.h", path))
+        let code = fs::read_to_string(format!("{}/master/ioctl.h", path))
             .expect("master/ioctl.h not found");
         let mut new = String::new();
+        let mut names = vec![];
         for line in code.split('\n') {
             let parts = line.split_whitespace().collect::<Vec<_>>();
             if parts.len() >= 3
@@ -41,19 +42,8 @@ fn main() {
                 && parts[1].starts_with("EC_IOCTL_")
                 && parts[2].starts_with("EC_IO")
             {
-                let name = &parts[1]["EC_IOCTL_".len()..];
-
-                // FIXME:
-                // There are constant names that start with a number
-                // so they would be invalid rust code.
-                // The problem exists e.g. if you try to
-                // compile the v1.5.2-sncn-11 branch of the synapticon
-                // fork: https://github.com/synapticon/Etherlab_EtherCAT_Master
-                // So as a first dirty workaround we just ignore them
-                // to be able to compile.
-                if name.starts_with("64_REF_CLK") {
-                    continue;
-                }
+                names.push(rust_ident(&parts[1]["EC_IOCTL_".len()..]));
+                let name = names[names.len() - 1].as_str();
 
                 let mut numparts = parts[2].split("(");
                 let access = match numparts.next().unwrap() {
@@ -74,9 +64,9 @@ fn main() {
                     "size_t" => "usize",
                     x => x,
                 });
-                write!(
+                writeln!(
                     &mut new,
-                    "ioctl!({:10} {:20} with EC, {}{}{});\n",
+                    "ioctl!({:10} {:20} with EC, {}{}{});",
                     access,
                     name,
                     number,
@@ -88,5 +78,27 @@ fn main() {
         }
         fs::write(out_path.join("ioctls.rs"), new.as_bytes())
             .expect("failed to write ioctls.rs bindings");
+
+        // Tell dependent crates (as `DEP_ETHERCAT_*`) about optional ioctls
+        // that not all versions of the master provide.
+        if ["REF_CLK_TIME_64", "REF_CLK_TIME_QUEUE_64"]
+            .iter()
+            .all(|n| names.iter().any(|name| name == n))
+        {
+            println!("cargo:ref_clk_time_64=1");
+        }
+    }
+}
+
+/// Turn an ioctl name into a valid Rust identifier.
+///
+/// Some names start with a number (e.g. `64_REF_CLK_TIME`),
+/// which is moved to the end instead (`REF_CLK_TIME_64`).
+fn rust_ident(name: &str) -> String {
+    let rest = name.trim_start_matches(|c: char| c.is_ascii_digit());
+    if rest.len() == name.len() {
+        return name.to_string();
     }
+    let digits = &name[..name.len() - rest.len()];
+    format!("{}_{}", rest.trim_start_matches('_'), digits)
 }
@@ -93,7 +93,7 @@ pub fn init_master(
             vendor_id: esi.vendor.id,
             product_code: dev.product_code,
         };
-        let mut config = master.configure_slave(slave_addr, slave_id)?;
+        let config = master.configure_slave(slave_addr, slave_id)?;
         let mut entry_offsets: HashMap<PdoEntryIndex, (u8, Offset)> = HashMap::new();
 
         let rx_pdos: Vec<PdoCfg> = dev
@@ -143,14 +143,14 @@ pub fn init_master(
         let output = SmCfg::output(2.into());
         let input = SmCfg::input(3.into());
 
-        config.config_sm_pdos(output, &rx_pdos)?;
-        config.config_sm_pdos(input, &tx_pdos)?;
+        config.config_sm_pdos(&master, output, &rx_pdos)?;
+        config.config_sm_pdos(&master, input, &tx_pdos)?;
 
         for pdo in &rx_pdos {
             // Positions of RX PDO
             log::debug!("Positions of RX PDO 0x{:X}:", u16::from(pdo.idx));
             for entry in &pdo.entries {
-                let offset = config.register_pdo_entry(entry.entry_idx, domain_idx)?;
+                let offset = config.register_pdo_entry(&master, entry.entry_idx, domain_idx)?;
                 entry_offsets.insert(entry.entry_idx, (entry.bit_len, offset));
             }
         }
@@ -158,7 +158,7 @@ pub fn init_master(
             // Positions of TX PDO
             log::debug!("Positions of TX PDO 0x{:X}:", u16::from(pdo.idx));
             for entry in &pdo.entries {
-                let offset = config.register_pdo_entry(entry.entry_idx, domain_idx)?;
+                let offset = config.register_pdo_entry(&master, entry.entry_idx, domain_idx)?;
                 entry_offsets.insert(entry.entry_idx, (entry.bit_len, offset));
             }
         }
@@ -1,7 +1,8 @@
 use ctrl_804::top_level::top_level::{DomainData, TopLevel};
 use ethercat::{
-    AlState, DomainIdx, Idx, Master, MasterAccess, Offset, PdoCfg, PdoEntryIdx, PdoEntryInfo,
-    PdoEntryPos, PdoIdx, SlaveAddr, SlaveId, SlavePos, SmCfg, SubIdx,
+    AlState, BusLayout, CyclicConfig, CyclicRunner, DcController, DomainIdx, ExpectedSlave, Idx,
+    Master, MasterAccess, Offset, PdoCfg, PdoEntryIdx, PdoEntryInfo, PdoEntryPos, PdoIdx,
+    RecoverySupervisor, SlaveAddr, SlaveId, SlavePos, SmCfg, SubIdx, WkcSupervisor,
 };
 use std::{collections::HashMap, convert::TryFrom, io};
 use tokio::time::{sleep_until, Duration, Instant};
@@ -10,41 +11,70 @@ use tokio::time::{sleep_until, Duration, Instant};
 pub async fn main() -> Result<(), std::io::Error> {
     env_logger::init();
     sleep_until(Instant::now() + Duration::from_millis(100)).await;
-    let (mut master, domain_idx) = init_master()?;
+    let (master, domain_idx, dc) = init_master()?;
 
-    // spawn a task to cyclically read data from the EtherCAT master
-    let handle = tokio::spawn(async move {
-        let cycle_time = Duration::from_micros(2_000);
-        let mut next_cycle = Instant::now() + cycle_time;
-        // let mut servo: Servo = Default::default();
-        let mut top_level: TopLevel = Default::default();
+    // run the cyclic data exchange on a dedicated real-time thread
+    let mut cfg = CyclicConfig::new(CYCLE_TIME);
+    cfg.domains = vec![domain_idx];
+    cfg.priority = Some(80);
+    cfg.lock_memory = true;
+    cfg.dc = Some(dc);
 
-        loop {
-            master.receive().unwrap();
-            master.domain(domain_idx).process().unwrap();
-            let m_state = master.state().unwrap();
-            // let _d_state = master.domain(domain_idx).state();
-            if m_state.link_up && AlState::try_from(m_state.al_states).unwrap() == AlState::Op {
-                // do something with the data
-                let raw_data: &mut [u8] = master.domain_data(domain_idx).unwrap();
-                use std::mem::transmute;
-                let raw_data_fixed: &mut DomainData = unsafe { transmute(raw_data.as_mut_ptr()) };
-                top_level.react(raw_data_fixed);
+    // let mut servo: Servo = Default::default();
+    let mut top_level: TopLevel = Default::default();
+    let mut wkc = WkcSupervisor::new(vec![domain_idx], 3)
+        .with_safe_state(move |master, ev| {
+            log::warn!("Working counter fault: {:?}", ev);
+            master.domain_data(domain_idx)?.fill(0);
+            Ok(())
+        })
+        .with_recovery(|_, ev| {
+            log::info!("Working counter recovered: {:?}", ev);
+            Ok(())
+        });
+    // slave configs are created in the order of SLAVE_SETTINGS
+    let mut recovery = RecoverySupervisor::new((0..SLAVE_SETTINGS.len() as u32).collect())
+        .with_recovery_hook(|_, config| {
+            log::info!(
+                "Slave config {} is operational again, re-home the axes",
+                config
+            );
+            Ok(())
+        });
+    let runner = CyclicRunner::spawn(master, cfg, move |master, info| {
+        let m_state = master.state()?;
+        wkc.check(master)?;
+        // the slave states are not updated every cycle, 100 ms is sufficient
+        if info.counter % 50 == 0 {
+            for event in recovery.check(master)? {
+                log::warn!("Slave recovery: {:?}", event);
             }
-            master.domain(domain_idx).queue().unwrap();
-            master.send().unwrap();
-
-            sleep_until(next_cycle).await;
-            next_cycle += cycle_time;
         }
-    });
+        if !wkc.is_faulted()
+            && m_state.link_up
+            && AlState::try_from(m_state.al_states).ok() == Some(AlState::Op)
+        {
+            // do something with the data
+            let raw_data: &mut [u8] = master.domain_data(domain_idx)?;
+            use std::mem::transmute;
+            let raw_data_fixed: &mut DomainData = unsafe { transmute(raw_data.as_mut_ptr()) };
+            top_level.react(raw_data_fixed);
+        }
+        Ok(())
+    })
+    .map_err(ethercat::Error::from)?;
 
     // join
-    handle.await.unwrap();
+    tokio::task::spawn_blocking(move || runner.join())
+        .await
+        .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?
+        .map_err(ethercat::Error::from)?;
 
     Ok(())
 }
 
+const CYCLE_TIME: Duration = Duration::from_micros(2_000);
+
 enum SlaveType {
     Servo,
     DigitalIO,
@@ -78,12 +108,28 @@ const SLAVE_SETTINGS: [SlaveSetting; 3] = [
     },
 ];
 
-fn init_master() -> Result<(Master, DomainIdx), io::Error> {
+fn init_master() -> Result<(Master, DomainIdx, DcController), io::Error> {
     log::debug!("open master0");
     let mut master = Master::open(0_u32, MasterAccess::ReadWrite)?;
     log::debug!("Reserve master");
     master.reserve()?;
 
+    let layout = BusLayout::new(
+        SLAVE_SETTINGS
+            .iter()
+            .map(|setting| {
+                let slave_id = SlaveId {
+                    vendor_id: setting.vendor_id,
+                    product_code: setting.product_code,
+                };
+                ExpectedSlave::new(SlaveAddr::ByPos(setting.slave_pos_u16), slave_id)
+            })
+            .collect(),
+    );
+    let report = master.verify_bus(&layout)?;
+    log::info!("Bus verification: {}", report);
+    report.into_result()?;
+
     log::debug!("Create domain");
     let domain_idx = master.create_domain()?;
     log::debug!("Create domain done {:?}", domain_idx);
@@ -104,7 +150,7 @@ fn init_master() -> Result<(Master, DomainIdx), io::Error> {
         };
         log::info!("Configure slave {:?}", slave_id);
 
-        let mut config = master.configure_slave(slave_addr, slave_id)?;
+        let config = master.configure_slave(slave_addr, slave_id)?;
         let mut entry_offsets: HashMap<PdoEntryIdx, (u8, Offset)> = HashMap::new();
         match setting.slave_type {
             SlaveType::DigitalIO => {
@@ -167,19 +213,20 @@ fn init_master() -> Result<(Master, DomainIdx), io::Error> {
                         },
                     ],
                 }];
-                config.clear_pdo_assignments(0.into())?;
-                config.clear_pdo_assignments(1.into())?;
-                config.clear_pdo_assignments(2.into())?;
+                config.clear_pdo_assignments(&master, 0.into())?;
+                config.clear_pdo_assignments(&master, 1.into())?;
+                config.clear_pdo_assignments(&master, 2.into())?;
 
-                config.config_sm_pdos(sm0, &sm0_pdos)?;
-                config.config_sm_pdos(sm1, &sm1_pdos)?;
-                config.config_sm_pdos(sm2, &sm2_pdos)?;
+                config.config_sm_pdos(&master, sm0, &sm0_pdos)?;
+                config.config_sm_pdos(&master, sm1, &sm1_pdos)?;
+                config.config_sm_pdos(&master, sm2, &sm2_pdos)?;
 
                 for pdo in &sm0_pdos {
                     // Positions of RX PDO
                     log::debug!("Positions of RX PDO 0x{:X}:", u16::from(pdo.idx));
                     for entry in &pdo.entries {
-                        let offset = config.register_pdo_entry(entry.entry_idx, domain_idx)?;
+                        let offset =
+                            config.register_pdo_entry(&master, entry.entry_idx, domain_idx)?;
                         entry_offsets.insert(entry.entry_idx, (entry.bit_len, offset));
                     }
                 }
@@ -187,7 +234,8 @@ fn init_master() -> Result<(Master, DomainIdx), io::Error> {
                     // Positions of RX PDO
                     log::debug!("Positions of RX PDO 0x{:X}:", u16::from(pdo.idx));
                     for entry in &pdo.entries {
-                        let offset = config.register_pdo_entry(entry.entry_idx, domain_idx)?;
+                        let offset =
+                            config.register_pdo_entry(&master, entry.entry_idx, domain_idx)?;
                         entry_offsets.insert(entry.entry_idx, (entry.bit_len, offset));
                     }
                 }
@@ -195,7 +243,8 @@ fn init_master() -> Result<(Master, DomainIdx), io::Error> {
                     // Positions of TX PDO
                     log::debug!("Positions of TX PDO 0x{:X}:", u16::from(pdo.idx));
                     for entry in &pdo.entries {
-                        let offset = config.register_pdo_entry(entry.entry_idx, domain_idx)?;
+                        let offset =
+                            config.register_pdo_entry(&master, entry.entry_idx, domain_idx)?;
                         entry_offsets.insert(entry.entry_idx, (entry.bit_len, offset));
                     }
                 }
@@ -297,19 +346,20 @@ fn init_master() -> Result<(Master, DomainIdx), io::Error> {
                     ],
                 }];
 
-                config.clear_emerg()?;
+                config.clear_emerg(&master)?;
 
-                config.clear_pdo_assignments(2.into())?;
-                config.clear_pdo_assignments(3.into())?;
+                config.clear_pdo_assignments(&master, 2.into())?;
+                config.clear_pdo_assignments(&master, 3.into())?;
 
-                config.config_sm_pdos(sm2, &sm2_pdos)?;
-                config.config_sm_pdos(sm3, &sm3_pdos)?;
+                config.config_sm_pdos(&master, sm2, &sm2_pdos)?;
+                config.config_sm_pdos(&master, sm3, &sm3_pdos)?;
 
                 for pdo in &sm2_pdos {
                     // Positions of RX PDO
                     log::debug!("Positions of RX PDO 0x{:X}:", u16::from(pdo.idx));
                     for entry in &pdo.entries {
-                        let offset = config.register_pdo_entry(entry.entry_idx, domain_idx)?;
+                        let offset =
+                            config.register_pdo_entry(&master, entry.entry_idx, domain_idx)?;
                         entry_offsets.insert(entry.entry_idx, (entry.bit_len, offset));
                     }
                 }
@@ -317,7 +367,8 @@ fn init_master() -> Result<(Master, DomainIdx), io::Error> {
                     // Positions of TX PDO
                     log::debug!("Positions of TX PDO 0x{:X}:", u16::from(pdo.idx));
                     for entry in &pdo.entries {
-                        let offset = config.register_pdo_entry(entry.entry_idx, domain_idx)?;
+                        let offset =
+                            config.register_pdo_entry(&master, entry.entry_idx, domain_idx)?;
                         entry_offsets.insert(entry.entry_idx, (entry.bit_len, offset));
                     }
                 }
@@ -336,18 +387,11 @@ fn init_master() -> Result<(Master, DomainIdx), io::Error> {
         offsets.insert(slave_pos, entry_offsets);
     }
 
-    // app_time nanoseconds from 2000, calc it
-    let app_time = std::time::SystemTime::now()
-        .duration_since(std::time::UNIX_EPOCH)
-        .unwrap()
-        .as_nanos() as u64
-        - 946_684_800_000_000_000_u64;
-
-    master.set_application_time(app_time)?;
-    master.sync_reference_clock()?;
-    master.sync_slave_clocks()?;
+    // the application time has to be known before activation
+    let dc = DcController::new(CYCLE_TIME, 1);
+    master.set_application_time(dc.app_time())?;
 
     master.activate()?;
 
-    Ok((master, domain_idx))
+    Ok((master, domain_idx, dc))
 }
@@ -0,0 +1,104 @@
+// Part of ethercat-rs. Copyright 2018-2020 by the authors.
+// This work is dual-licensed under Apache 2.0 and MIT terms.
+
+use std::fmt;
+
+/// Register with the AL status of a slave.
+pub(crate) const AL_STATUS_REG: u16 = 0x0130;
+/// Register with the AL status code of a slave.
+pub(crate) const AL_STATUS_CODE_REG: u16 = 0x0134;
+/// Error indication bit in the AL status register.
+pub(crate) const AL_STATUS_ERROR: u8 = 0x10;
+
+/// AL status code (register 0x0134), the reason why a slave refused a
+/// state transition or left its state.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct AlStatusCode(pub u16);
+
+impl AlStatusCode {
+    pub const NO_ERROR: Self = Self(0x0000);
+
+    /// Description of the code as specified in ETG.1000.6.
+    pub fn description(self) -> Option<&'static str> {
+        Some(match self.0 {
+            0x0000 => "no error",
+            0x0001 => "unspecified error",
+            0x0002 => "no memory",
+            0x0003 => "invalid device setup",
+            0x0011 => "invalid requested state change",
+            0x0012 => "unknown requested state",
+            0x0013 => "bootstrap not supported",
+            0x0014 => "no valid firmware",
+            0x0015 => "invalid mailbox configuration (bootstrap)",
+            0x0016 => "invalid mailbox configuration (pre-operational)",
+            0x0017 => "invalid sync manager configuration",
+            0x0018 => "no valid inputs available",
+            0x0019 => "no valid outputs",
+            0x001A => "synchronization error",
+            0x001B => "sync manager watchdog",
+            0x001C => "invalid sync manager types",
+            0x001D => "invalid output configuration",
+            0x001E => "invalid input configuration",
+            0x001F => "invalid watchdog configuration",
+            0x0020 => "slave needs cold start",
+            0x0021 => "slave needs INIT",
+            0x0022 => "slave needs PREOP",
+            0x0023 => "slave needs SAFEOP",
+            0x0024 => "invalid input mapping",
+            0x0025 => "invalid output mapping",
+            0x0026 => "inconsistent settings",
+            0x0027 => "free-run not supported",
+            0x0028 => "synchronization not supported",
+            0x0029 => "free-run needs 3-buffer mode",
+            0x002A => "background watchdog",
+            0x002B => "no valid inputs and outputs",
+            0x002C => "fatal sync error",
+            0x002D => "no sync error",
+            0x0030 => "invalid DC SYNC configuration",
+            0x0031 => "invalid DC latch configuration",
+            0x0032 => "PLL error",
+            0x0033 => "DC sync IO error",
+            0x0034 => "DC sync timeout error",
+            0x0035 => "DC invalid sync cycle time",
+            0x0036 => "DC sync0 cycle time",
+            0x0037 => "DC sync1 cycle time",
+            0x0041 => "mailbox AoE",
+            0x0042 => "mailbox EoE",
+            0x0043 => "mailbox CoE",
+            0x0044 => "mailbox FoE",
+            0x0045 => "mailbox SoE",
+            0x004F => "mailbox VoE",
+            0x0050 => "EEPROM no access",
+            0x0051 => "EEPROM error",
+            0x0060 => "slave restarted locally",
+            0x0061 => "device identification value updated",
+            0x00F0 => "application controller available",
+            _ => return None,
+        })
+    }
+
+    /// Codes from 0x8000 are defined by the vendor of the device.
+    pub const fn is_vendor_specific(self) -> bool {
+        self.0 >= 0x8000
+    }
+}
+
+impl fmt::Display for AlStatusCode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.description() {
+            Some(desc) => write!(f, "{:#06x} ({})", self.0, desc),
+            None if self.is_vendor_specific() => write!(f, "{:#06x} (vendor specific)", self.0),
+            None => write!(f, "{:#06x}", self.0),
+        }
+    }
+}
+
+#[test]
+fn test_al_status_code() {
+    assert_eq!(
+        AlStatusCode(0x1B).to_string(),
+        "0x001b (sync manager watchdog)"
+    );
+    assert_eq!(AlStatusCode(0x8001).to_string(), "0x8001 (vendor specific)");
+    assert_eq!(AlStatusCode(0x7F).to_string(), "0x007f");
+}
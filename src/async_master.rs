@@ -0,0 +1,147 @@
+// Part of ethercat-rs. Copyright 2018-2020 by the authors.
+// This work is dual-licensed under Apache 2.0 and MIT terms.
+
+use crate::{
+    al_status::AlStatusCode,
+    master::{Master, MasterAccess},
+    types::*,
+};
+use std::{
+    sync::{mpsc, Arc, Mutex},
+    thread,
+    time::Duration,
+};
+use tokio::sync::oneshot;
+
+type Job = Box<dyn FnOnce(&mut Master) + Send>;
+
+/// Async access to the blocking mailbox and state APIs of a master.
+///
+/// The requests are executed by worker threads, each with its own handle
+/// to the master, so that they do not block the executor. With several
+/// workers, requests to different slaves are processed in parallel.
+///
+/// The handles do not request the master, so this can be used next to the
+/// application that runs the cyclic task. The workers exit after their
+/// current request when the `AsyncMaster` is dropped.
+pub struct AsyncMaster {
+    jobs: mpsc::Sender<Job>,
+}
+
+impl AsyncMaster {
+    /// Open the master `workers` times and start a worker thread for each handle.
+    pub fn open(idx: MasterIdx, access: MasterAccess, workers: usize) -> Result<Self> {
+        let masters = (0..workers.max(1))
+            .map(|_| Master::open(idx, access))
+            .collect::<Result<Vec<_>>>()?;
+        let (tx, rx) = mpsc::channel::<Job>();
+        let rx = Arc::new(Mutex::new(rx));
+        for (i, mut master) in masters.into_iter().enumerate() {
+            let rx = rx.clone();
+            thread::Builder::new()
+                .name(format!("ethercat-mailbox-{}", i))
+                .spawn(move || loop {
+                    let job = match rx.lock() {
+                        Ok(rx) => rx.recv(),
+                        Err(_) => return,
+                    };
+                    match job {
+                        Ok(job) => job(&mut master),
+                        Err(_) => return,
+                    }
+                })?;
+        }
+        Ok(Self { jobs: tx })
+    }
+
+    /// Run a blocking operation on a worker and wait for its result.
+    pub async fn run<F, T>(&self, f: F) -> Result<T>
+    where
+        F: FnOnce(&mut Master) -> Result<T> + Send + 'static,
+        T: Send + 'static,
+    {
+        let (tx, rx) = oneshot::channel();
+        let job: Job = Box::new(move |master| {
+            let _ = tx.send(f(master));
+        });
+        self.jobs.send(job).map_err(|_| Error::WorkerStopped)?;
+        rx.await.map_err(|_| Error::WorkerStopped)?
+    }
+
+    /// Upload an SDO of at most `max_size` bytes.
+    pub async fn sdo_upload(
+        &self,
+        position: SlavePos,
+        sdo_idx: SdoIdx,
+        complete_access: bool,
+        max_size: usize,
+    ) -> Result<Vec<u8>> {
+        self.run(move |master| {
+            let mut data = vec![0; max_size];
+            let len = master
+                .sdo_upload(position, sdo_idx, complete_access, &mut data)?
+                .len();
+            data.truncate(len);
+            Ok(data)
+        })
+        .await
+    }
+
+    pub async fn sdo_download(
+        &self,
+        position: SlavePos,
+        sdo_idx: SdoIdx,
+        complete_access: bool,
+        data: Vec<u8>,
+    ) -> Result<()> {
+        self.run(move |master| master.sdo_download(position, sdo_idx, complete_access, &&data[..]))
+            .await
+    }
+
+    pub async fn get_sdo(&self, slave_pos: SlavePos, sdo_pos: SdoPos) -> Result<SdoInfo> {
+        self.run(move |master| master.get_sdo(slave_pos, sdo_pos))
+            .await
+    }
+
+    pub async fn get_sdo_entry(
+        &self,
+        slave_pos: SlavePos,
+        addr: SdoEntryAddr,
+    ) -> Result<SdoEntryInfo> {
+        self.run(move |master| master.get_sdo_entry(slave_pos, addr))
+            .await
+    }
+
+    pub async fn request_state(&self, slave_pos: SlavePos, state: AlState) -> Result<()> {
+        self.run(move |master| master.request_state(slave_pos, state))
+            .await
+    }
+
+    /// See [`Master::request_state_and_wait`].
+    pub async fn request_state_and_wait(
+        &self,
+        slave_pos: SlavePos,
+        state: AlState,
+        timeout: Duration,
+    ) -> Result<()> {
+        self.run(move |master| master.request_state_and_wait(slave_pos, state, timeout))
+            .await
+    }
+
+    pub async fn al_status_code(&self, slave_pos: SlavePos) -> Result<AlStatusCode> {
+        self.run(move |master| master.al_status_code(slave_pos))
+            .await
+    }
+}
+
+#[test]
+fn test_async_master_is_shareable() {
+    fn assert_send<T: Send>(_: &T) {}
+    fn assert_sync<T: Sync>() {}
+    #[allow(dead_code)]
+    fn check(master: &AsyncMaster) {
+        assert_send(&master.sdo_upload(SlavePos::from(0), SdoIdx::new(0x1018, 1), false, 4));
+        assert_send(&master.request_state(SlavePos::from(0), AlState::Op));
+    }
+    assert_sync::<AsyncMaster>();
+}
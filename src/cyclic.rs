@@ -0,0 +1,513 @@
+// Part of ethercat-rs. Copyright 2018-2020 by the authors.
+// This work is dual-licensed under Apache 2.0 and MIT terms.
+
+use crate::{
+    dc::{DcController, EpochClock},
+    master::Master,
+    rt,
+    schedule::DomainScheduler,
+    stats::CycleStats,
+    types::*,
+};
+use std::{
+    fmt,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc, Arc, Mutex,
+    },
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+use thiserror::Error;
+
+/// Configuration of a [`CyclicRunner`].
+#[derive(Debug, Clone)]
+pub struct CyclicConfig {
+    /// Cycle period.
+    pub period: Duration,
+    /// Domains that are processed and queued in every cycle.
+    pub domains: Vec<DomainIdx>,
+    /// Domains that are exchanged with a cycle divisor.
+    pub schedule: DomainScheduler,
+    /// `SCHED_FIFO` priority of the cyclic thread (`None` keeps the default scheduler).
+    pub priority: Option<i32>,
+    /// CPUs the cyclic thread is pinned to (empty means no affinity).
+    pub cpus: Vec<usize>,
+    /// Lock all current and future pages of the process into RAM.
+    pub lock_memory: bool,
+    /// Distributed clock synchronisation done in every cycle.
+    pub dc: Option<DcController>,
+    /// What to do with the wake-ups that were missed by an overrunning cycle.
+    pub overrun_policy: OverrunPolicy,
+    /// Number of consecutive overruns after which the fault hook is invoked.
+    ///
+    /// The hook is invoked once when the limit is reached; it is armed again
+    /// after a cycle without overrun. With [`OverrunPolicy::CatchUp`], the
+    /// cycles that run back to back after an overrun only count as overruns
+    /// if they take longer than a period themselves.
+    pub overrun_limit: Option<u32>,
+}
+
+impl CyclicConfig {
+    pub fn new(period: Duration) -> Self {
+        Self {
+            period,
+            domains: vec![],
+            schedule: DomainScheduler::new(),
+            priority: None,
+            cpus: vec![],
+            lock_memory: false,
+            dc: None,
+            overrun_policy: OverrunPolicy::Skip,
+            overrun_limit: None,
+        }
+    }
+}
+
+/// Behaviour of the [`CyclicRunner`] after a cycle missed its deadline.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OverrunPolicy {
+    /// Drop the missed wake-ups and continue at the next period boundary.
+    Skip,
+    /// Run the missed cycles back to back until the schedule has caught up.
+    CatchUp,
+}
+
+/// A cycle that did not finish before the next scheduled wake-up.
+#[derive(Debug, Clone, Copy)]
+pub struct OverrunEvent {
+    /// Number of the overrunning cycle.
+    pub cycle: u64,
+    /// Time by which the next wake-up was missed.
+    pub lateness: Duration,
+    /// Number of consecutive overruns including this one.
+    pub consecutive: u32,
+    /// Number of wake-ups dropped because of this overrun.
+    pub skipped: u64,
+}
+
+/// Information passed to the user callback in every cycle.
+#[derive(Debug, Clone, Copy)]
+pub struct CycleInfo<'a> {
+    /// Number of the current cycle, starting at 0.
+    pub counter: u64,
+    /// Application time of this cycle in nanoseconds since 2000-01-01.
+    pub app_time: u64,
+    /// Set if the previous cycle overran.
+    pub overrun: Option<OverrunEvent>,
+    /// All domains with their update rates and working counter states.
+    pub domains: &'a DomainScheduler,
+}
+
+/// Runs the EtherCAT process data exchange on a dedicated real-time thread.
+///
+/// Every cycle the thread sleeps until the next absolute wake-up time on
+/// `CLOCK_MONOTONIC`, then receives the frames, processes the domains that
+/// were queued in the previous cycle, calls the user callback, syncs the
+/// distributed clocks (if a [`DcController`] is configured), queues the
+/// domains that are due and sends the frames.
+///
+/// The timing of every cycle is recorded in [`CycleStats`] which can be
+/// fetched with [`CyclicRunner::stats`]. Cycles that miss their deadline are
+/// handled according to the configured [`OverrunPolicy`] and reported to the
+/// next callback invocation in [`CycleInfo::overrun`].
+///
+/// The master must be activated before it is handed over to the runner.
+pub struct CyclicRunner {
+    shared: Arc<Shared>,
+    thread: Option<JoinHandle<std::result::Result<Master, CycleError>>>,
+}
+
+/// Error of [`CyclicRunner::spawn`] that hands back the master, e.g. to
+/// continue without real-time scheduling when the process lacks the
+/// privileges for it.
+#[derive(Error)]
+#[error("Failed to spawn the cyclic task: {error}")]
+pub struct SpawnError {
+    #[source]
+    pub error: Error,
+    pub master: Master,
+}
+
+impl fmt::Debug for SpawnError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("SpawnError")
+            .field("error", &self.error)
+            .finish_non_exhaustive()
+    }
+}
+
+impl From<SpawnError> for Error {
+    fn from(e: SpawnError) -> Self {
+        e.error
+    }
+}
+
+/// Error that terminated the cyclic task, returned by [`CyclicRunner::join`].
+///
+/// The master is handed back to bring the bus into a safe state, unless
+/// the cyclic task panicked.
+#[derive(Error)]
+#[error("Cyclic task failed: {error}")]
+pub struct CycleError {
+    #[source]
+    pub error: Error,
+    pub master: Option<Master>,
+}
+
+impl CycleError {
+    const fn panicked() -> Self {
+        Self {
+            error: Error::CyclicTaskPanicked,
+            master: None,
+        }
+    }
+}
+
+impl fmt::Debug for CycleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("CycleError")
+            .field("error", &self.error)
+            .finish_non_exhaustive()
+    }
+}
+
+impl From<CycleError> for Error {
+    fn from(e: CycleError) -> Self {
+        e.error
+    }
+}
+
+/// State shared between the runner handle and the cyclic thread.
+struct Shared {
+    stop: AtomicBool,
+    reset_stats: AtomicBool,
+    stats: Mutex<CycleStats>,
+}
+
+impl CyclicRunner {
+    /// Spawn the cyclic thread.
+    ///
+    /// If the [`CyclicConfig::overrun_limit`] is reached, the cycle stops
+    /// with [`Error::CycleOverrun`].
+    ///
+    /// Returns an error with the master if the period is zero or the
+    /// real-time setup (memory locking, scheduling priority or CPU affinity)
+    /// fails.
+    pub fn spawn<F>(
+        master: Master,
+        cfg: CyclicConfig,
+        callback: F,
+    ) -> std::result::Result<Self, SpawnError>
+    where
+        F: FnMut(&mut Master, &CycleInfo<'_>) -> Result<()> + Send + 'static,
+    {
+        Self::spawn_with_fault_hook(master, cfg, callback, |_, ev| {
+            Err(Error::CycleOverrun(ev.consecutive))
+        })
+    }
+
+    /// Spawn the cyclic thread with a hook that is invoked when the
+    /// [`CyclicConfig::overrun_limit`] is reached, i.e. in the cycle that
+    /// overran that many times in a row.
+    ///
+    /// The hook can bring the machine into a safe state; if it returns
+    /// an error, the cycle stops.
+    pub fn spawn_with_fault_hook<F, H>(
+        master: Master,
+        cfg: CyclicConfig,
+        callback: F,
+        fault_hook: H,
+    ) -> std::result::Result<Self, SpawnError>
+    where
+        F: FnMut(&mut Master, &CycleInfo<'_>) -> Result<()> + Send + 'static,
+        H: FnMut(&mut Master, &OverrunEvent) -> Result<()> + Send + 'static,
+    {
+        if cfg.period.is_zero() {
+            return Err(SpawnError {
+                error: Error::InvalidCyclePeriod,
+                master,
+            });
+        }
+        if cfg.lock_memory {
+            if let Err(error) = rt::lock_memory() {
+                return Err(SpawnError { error, master });
+            }
+        }
+        let shared = Arc::new(Shared {
+            stop: AtomicBool::new(false),
+            reset_stats: AtomicBool::new(false),
+            stats: Mutex::new(CycleStats::new(cfg.period)),
+        });
+        let (setup_tx, setup_rx) = mpsc::sync_channel(1);
+        // The master is only handed over once the thread setup succeeded.
+        let (master_tx, master_rx) = mpsc::sync_channel(1);
+        let spawned = {
+            let shared = shared.clone();
+            thread::Builder::new()
+                .name("ethercat-cyclic".into())
+                .spawn(move || {
+                    let _ = setup_tx.send(setup_thread(&cfg));
+                    let mut master = master_rx.recv().map_err(|_| CycleError::panicked())?;
+                    match run(&mut master, &cfg, &shared, callback, fault_hook) {
+                        Ok(()) => Ok(master),
+                        Err(error) => Err(CycleError {
+                            error,
+                            master: Some(master),
+                        }),
+                    }
+                })
+        };
+        let thread = match spawned {
+            Ok(thread) => thread,
+            Err(e) => {
+                return Err(SpawnError {
+                    error: e.into(),
+                    master,
+                })
+            }
+        };
+        let error = match setup_rx.recv() {
+            Ok(Ok(())) => match master_tx.send(master) {
+                Ok(()) => {
+                    return Ok(Self {
+                        shared,
+                        thread: Some(thread),
+                    })
+                }
+                Err(mpsc::SendError(master)) => {
+                    let _ = thread.join();
+                    return Err(SpawnError {
+                        error: Error::CyclicTaskPanicked,
+                        master,
+                    });
+                }
+            },
+            Ok(Err(e)) => e,
+            Err(_) => Error::CyclicTaskPanicked,
+        };
+        drop(master_tx);
+        let _ = thread.join();
+        Err(SpawnError { error, master })
+    }
+
+    /// Ask the cyclic thread to stop after the current cycle.
+    pub fn stop(&self) {
+        self.shared.stop.store(true, Ordering::Release);
+    }
+
+    /// A snapshot of the timing statistics.
+    pub fn stats(&self) -> CycleStats {
+        *self.shared.stats.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Clear the timing statistics at the end of the next cycle.
+    pub fn reset_stats(&self) {
+        self.shared.reset_stats.store(true, Ordering::Release);
+    }
+
+    /// Returns `true` while the cyclic thread is alive.
+    pub fn is_running(&self) -> bool {
+        self.thread.as_ref().is_some_and(|t| !t.is_finished())
+    }
+
+    /// Stop the cyclic thread and wait for it to finish.
+    ///
+    /// Returns the master, or the error that terminated the cycle
+    /// together with the master.
+    pub fn join(mut self) -> std::result::Result<Master, CycleError> {
+        self.stop();
+        self.thread
+            .take()
+            .ok_or_else(CycleError::panicked)?
+            .join()
+            .map_err(|_| CycleError::panicked())?
+    }
+}
+
+impl Drop for CyclicRunner {
+    fn drop(&mut self) {
+        if let Some(thread) = self.thread.take() {
+            self.stop();
+            let _ = thread.join();
+        }
+    }
+}
+
+fn setup_thread(cfg: &CyclicConfig) -> Result<()> {
+    if !cfg.cpus.is_empty() {
+        rt::set_cpu_affinity(&cfg.cpus)?;
+    }
+    if let Some(priority) = cfg.priority {
+        rt::set_fifo_priority(priority)?;
+    }
+    Ok(())
+}
+
+fn run<F, H>(
+    master: &mut Master,
+    cfg: &CyclicConfig,
+    shared: &Shared,
+    mut callback: F,
+    mut fault_hook: H,
+) -> Result<()>
+where
+    F: FnMut(&mut Master, &CycleInfo<'_>) -> Result<()>,
+    H: FnMut(&mut Master, &OverrunEvent) -> Result<()>,
+{
+    let period = cfg.period.as_nanos() as u64;
+    let mut dc = cfg.dc.clone();
+    let clock = EpochClock::now();
+    let mut wakeup = rt::monotonic_ns() + period;
+    let mut counter = 0;
+    let mut stats = CycleStats::new(cfg.period);
+    let mut overruns = OverrunTracker::new(cfg.overrun_policy, period);
+    let mut last_overrun = None;
+    let mut domains = cfg.schedule.clone();
+    for &idx in &cfg.domains {
+        domains.add(idx, 1, 0);
+    }
+
+    while !shared.stop.load(Ordering::Acquire) {
+        rt::sleep_until_ns(wakeup)?;
+        let t_wakeup = rt::monotonic_ns();
+
+        master.receive()?;
+        domains.process(master)?;
+        let t_received = rt::monotonic_ns();
+
+        let info = CycleInfo {
+            counter,
+            app_time: match &dc {
+                Some(dc) => dc.app_time_at(wakeup),
+                None => clock.at(wakeup),
+            },
+            overrun: last_overrun.take(),
+            domains: &domains,
+        };
+        callback(master, &info)?;
+        let t_executed = rt::monotonic_ns();
+
+        if let Some(dc) = &mut dc {
+            dc.sync(master, info.app_time)?;
+        }
+        domains.queue(master, counter)?;
+        master.send()?;
+        let t_sent = rt::monotonic_ns();
+
+        if shared.reset_stats.swap(false, Ordering::AcqRel) {
+            stats.reset();
+        }
+        stats.record_ns(
+            t_wakeup.saturating_sub(wakeup),
+            t_received - t_wakeup,
+            t_executed - t_received,
+            t_sent - t_executed,
+        );
+        // Never block the cycle: skip publishing if a reader holds the lock.
+        if let Ok(mut shared_stats) = shared.stats.try_lock() {
+            *shared_stats = stats;
+        }
+
+        let (next_wakeup, overrun) = overruns.next_wakeup(counter, wakeup, t_sent);
+        if let Some(ev) = overrun {
+            if cfg.overrun_limit == Some(ev.consecutive) {
+                log::error!("{} consecutive cycle overruns", ev.consecutive);
+                fault_hook(master, &ev)?;
+            }
+        }
+        last_overrun = overrun;
+        wakeup = match &dc {
+            Some(dc) => (next_wakeup as i64 + dc.cycle_adjustment()) as u64,
+            None => next_wakeup,
+        };
+        counter += 1;
+    }
+    Ok(())
+}
+
+/// Detects overruns and computes the next wake-up according to the policy.
+struct OverrunTracker {
+    policy: OverrunPolicy,
+    period: u64,
+    consecutive: u32,
+    /// End of the previous cycle.
+    last_end: u64,
+}
+
+impl OverrunTracker {
+    const fn new(policy: OverrunPolicy, period: u64) -> Self {
+        Self {
+            policy,
+            period,
+            consecutive: 0,
+            last_end: 0,
+        }
+    }
+
+    /// Returns the next wake-up time and the overrun event, if the
+    /// cycle that was scheduled for `wakeup` ended after the next one.
+    fn next_wakeup(&mut self, cycle: u64, wakeup: u64, end: u64) -> (u64, Option<OverrunEvent>) {
+        let next = wakeup + self.period;
+        // A cycle catching up after an overrun starts late, so it is only
+        // an overrun if it takes longer than a period by itself.
+        let deadline = wakeup.max(self.last_end) + self.period;
+        self.last_end = end;
+        if end <= deadline {
+            self.consecutive = 0;
+            return (next, None);
+        }
+        self.consecutive = self.consecutive.saturating_add(1);
+        let lateness = end - next;
+        let skipped = match self.policy {
+            OverrunPolicy::Skip => lateness / self.period + 1,
+            OverrunPolicy::CatchUp => 0,
+        };
+        let ev = OverrunEvent {
+            cycle,
+            lateness: Duration::from_nanos(lateness),
+            consecutive: self.consecutive,
+            skipped,
+        };
+        (next + skipped * self.period, Some(ev))
+    }
+}
+
+#[test]
+fn test_overrun_tracker() {
+    let mut skip = OverrunTracker::new(OverrunPolicy::Skip, 1_000);
+    assert!(matches!(skip.next_wakeup(0, 1_000, 1_800), (2_000, None)));
+    let (next, ev) = skip.next_wakeup(1, 2_000, 5_500);
+    let ev = ev.unwrap();
+    assert_eq!(next, 6_000);
+    assert_eq!(ev.lateness, Duration::from_nanos(2_500));
+    assert_eq!(ev.skipped, 3);
+    assert_eq!(ev.consecutive, 1);
+    let (_, ev) = skip.next_wakeup(2, 6_000, 7_001);
+    assert_eq!(ev.unwrap().consecutive, 2);
+    assert!(skip.next_wakeup(3, 7_000, 7_500).1.is_none());
+    assert_eq!(skip.consecutive, 0);
+
+    let mut catch_up = OverrunTracker::new(OverrunPolicy::CatchUp, 1_000);
+    let (next, ev) = catch_up.next_wakeup(0, 1_000, 4_500);
+    assert_eq!(next, 2_000);
+    assert_eq!(ev.unwrap().skipped, 0);
+    // the back to back cycles after a single stall are no new overruns
+    assert!(matches!(
+        catch_up.next_wakeup(1, 2_000, 4_600),
+        (3_000, None)
+    ));
+    assert!(matches!(
+        catch_up.next_wakeup(2, 3_000, 4_700),
+        (4_000, None)
+    ));
+    assert_eq!(catch_up.consecutive, 0);
+    // unless they take longer than a period themselves
+    let (next, ev) = catch_up.next_wakeup(3, 4_000, 5_800);
+    assert_eq!(next, 5_000);
+    assert_eq!(ev.unwrap().consecutive, 1);
+    assert!(matches!(
+        catch_up.next_wakeup(4, 5_000, 5_900),
+        (6_000, None)
+    ));
+}
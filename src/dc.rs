@@ -0,0 +1,548 @@
+// Part of ethercat-rs. Copyright 2018-2020 by the authors.
+// This work is dual-licensed under Apache 2.0 and MIT terms.
+
+use crate::{master::Master, rt, types::*};
+use std::{
+    convert::TryFrom,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// Offset between the Unix epoch and the EtherCAT epoch (2000-01-01) in nanoseconds.
+const EC_EPOCH_OFFSET_NS: u64 = 946_684_800_000_000_000;
+
+/// Number of clock samples that are averaged in bus shift mode.
+const DC_FILTER_CNT: i64 = 1024;
+
+/// Maximum correction of the master cycle per cycle in bus shift mode.
+const DC_MAX_ADJUST_NS: i64 = 1_000;
+
+/// Maps `CLOCK_MONOTONIC` to nanoseconds since the EtherCAT epoch.
+///
+/// The offset is taken from the wall clock once, so later steps of the
+/// wall clock (NTP, manual changes) do not affect the application time.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct EpochClock {
+    offset_ns: u64,
+}
+
+impl EpochClock {
+    pub(crate) fn now() -> Self {
+        let unix = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos() as u64;
+        let ethercat = unix.saturating_sub(EC_EPOCH_OFFSET_NS);
+        Self {
+            offset_ns: ethercat.wrapping_sub(rt::monotonic_ns()),
+        }
+    }
+
+    pub(crate) const fn at(&self, monotonic_ns: u64) -> u64 {
+        monotonic_ns.wrapping_add(self.offset_ns)
+    }
+}
+
+/// Distributed clock synchronisation of the master.
+///
+/// In every cycle [`DcController::sync`] sets the application time and
+/// queues the datagrams to sync the reference clock to the master and the
+/// slave clocks to the reference clock (every `sync_divisor` cycles).
+///
+/// In bus shift mode, the reference clock is not synced to the master.
+/// Instead, the time of the reference clock is read back and the master
+/// cycle is adjusted to follow it (see [`DcController::cycle_adjustment`]).
+#[derive(Debug, Clone)]
+pub struct DcController {
+    clock: EpochClock,
+    period_ns: i64,
+    sync_divisor: u32,
+    bus_shift: bool,
+    counter: u32,
+    last_sync_app_time: Option<u64>,
+    reference_clock_time: Option<u32>,
+    time_base: i64,
+    adjustment: i64,
+    filter: DriftFilter,
+}
+
+impl DcController {
+    /// Create a controller for the given master cycle period.
+    ///
+    /// The clocks are synced every `sync_divisor` cycles (0 is treated as 1).
+    pub fn new(period: Duration, sync_divisor: u32) -> Self {
+        Self {
+            clock: EpochClock::now(),
+            period_ns: period.as_nanos() as i64,
+            sync_divisor: sync_divisor.max(1),
+            bus_shift: false,
+            counter: 0,
+            last_sync_app_time: None,
+            reference_clock_time: None,
+            time_base: 0,
+            adjustment: 0,
+            filter: DriftFilter::default(),
+        }
+    }
+
+    /// Let the master cycle follow the reference clock instead of
+    /// syncing the reference clock to the master.
+    pub fn set_bus_shift(&mut self, enable: bool) {
+        self.bus_shift = enable;
+    }
+
+    pub const fn bus_shift(&self) -> bool {
+        self.bus_shift
+    }
+
+    /// Current application time in nanoseconds since 2000-01-01.
+    pub fn app_time(&self) -> u64 {
+        self.app_time_at(rt::monotonic_ns())
+    }
+
+    /// Application time at the given `CLOCK_MONOTONIC` time.
+    pub(crate) fn app_time_at(&self, monotonic_ns: u64) -> u64 {
+        self.clock
+            .at(monotonic_ns)
+            .wrapping_sub(self.time_base as u64)
+    }
+
+    /// Set the application time and sync the clocks.
+    ///
+    /// Must be called in every cycle before the frames are sent.
+    pub fn sync(&mut self, master: &mut Master, app_time: u64) -> Result<()> {
+        master.set_application_time(app_time)?;
+        self.adjustment = 0;
+        if self.counter == 0 {
+            if self.bus_shift {
+                // The reference clock time was transmitted with the last
+                // slave clock sync datagram; it is not available before
+                // the first one was received.
+                if let Ok(ref_time) = master.get_reference_clock_time() {
+                    self.reference_clock_time = Some(ref_time);
+                    if let Some(last) = self.last_sync_app_time {
+                        self.adjustment = self.filter.update(last, ref_time, self.period_ns);
+                        self.time_base += self.adjustment;
+                    }
+                }
+            } else {
+                master.sync_reference_clock()?;
+            }
+            master.sync_slave_clocks()?;
+            self.last_sync_app_time = Some(app_time);
+        }
+        self.counter = (self.counter + 1) % self.sync_divisor;
+        Ok(())
+    }
+
+    /// The last reference clock time read in bus shift mode.
+    pub const fn reference_clock_time(&self) -> Option<u32> {
+        self.reference_clock_time
+    }
+
+    /// Difference between the application time and the reference clock
+    /// in the last sample of bus shift mode, normalised to ±period/2.
+    pub const fn clock_diff(&self) -> i32 {
+        self.filter.diff
+    }
+
+    /// Correction in nanoseconds that has to be added to the next
+    /// wake-up time of the master cycle (always 0 without bus shift).
+    pub const fn cycle_adjustment(&self) -> i64 {
+        self.adjustment
+    }
+}
+
+/// Drift filter of the bus shift mode, following the IgH `dc_rtai` example.
+#[derive(Debug, Clone, Default)]
+struct DriftFilter {
+    started: bool,
+    diff: i32,
+    raw_diff: i32,
+    diff_total: i64,
+    delta_total: i64,
+    idx: i64,
+    adjust: i64,
+}
+
+impl DriftFilter {
+    fn update(&mut self, app_time: u64, ref_time: u32, period_ns: i64) -> i64 {
+        let raw_diff = (app_time as u32).wrapping_sub(ref_time) as i32;
+        let delta = raw_diff.wrapping_sub(self.raw_diff) as i64;
+        self.raw_diff = raw_diff;
+        let diff = (raw_diff as i64 + period_ns / 2).rem_euclid(period_ns) - period_ns / 2;
+        self.diff = diff as i32;
+
+        if !self.started {
+            self.started = diff != 0;
+            return 0;
+        }
+        self.diff_total += diff;
+        self.delta_total += delta;
+        self.idx += 1;
+        if self.idx >= DC_FILTER_CNT {
+            self.adjust += (self.delta_total + DC_FILTER_CNT / 2) / DC_FILTER_CNT;
+            self.adjust += (self.diff_total / DC_FILTER_CNT).signum();
+            self.adjust = self.adjust.clamp(-DC_MAX_ADJUST_NS, DC_MAX_ADJUST_NS);
+            self.diff_total = 0;
+            self.delta_total = 0;
+            self.idx = 0;
+        }
+        self.adjust + diff.signum()
+    }
+}
+
+/// Value returned by the sync monitor if the measurement was not received.
+const SYNC_MON_LOST: u32 = 0xFFFF_FFFF;
+
+/// Synchronicity of the distributed clocks as seen by the [`DcMonitor`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DcSyncState {
+    /// No measurement was processed yet.
+    Unknown,
+    /// The system time difference is within the threshold.
+    InSync,
+    /// The system time difference exceeds the threshold.
+    OutOfSync,
+    /// The measurement datagram was not received.
+    Lost,
+}
+
+/// A change of the [`DcSyncState`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DcSyncEvent {
+    InSync { diff: Duration },
+    OutOfSync { diff: Duration },
+    Lost,
+}
+
+/// Monitors the synchronicity of the slave clocks.
+///
+/// Call [`DcMonitor::queue`] in every cycle before sending the frames and
+/// [`DcMonitor::process`] after receiving them. The monitor records the
+/// upper estimation of the system time difference of all DC slaves
+/// (register 0x092C) over a window of cycles.
+#[derive(Debug, Clone)]
+pub struct DcMonitor {
+    threshold_ns: u32,
+    window: usize,
+    samples: Vec<u32>,
+    next: usize,
+    state: DcSyncState,
+}
+
+impl DcMonitor {
+    /// Create a monitor that keeps the last `window` samples and considers
+    /// the clocks out of sync above `threshold`.
+    pub fn new(window: usize, threshold: Duration) -> Self {
+        Self {
+            threshold_ns: threshold.as_nanos().min(u128::from(SYNC_MON_LOST - 1)) as u32,
+            window: window.max(1),
+            samples: Vec::with_capacity(window.max(1)),
+            next: 0,
+            state: DcSyncState::Unknown,
+        }
+    }
+
+    /// Queue the measurement datagram.
+    pub fn queue(&self, master: &mut Master) -> Result<()> {
+        master.sync_monitor_queue()
+    }
+
+    /// Read the result of the last measurement.
+    ///
+    /// Returns an event if the sync state has changed.
+    pub fn process(&mut self, master: &mut Master) -> Result<Option<DcSyncEvent>> {
+        let raw = master.sync_monitor_process()?;
+        Ok(self.update(raw))
+    }
+
+    /// Record a raw value as returned by [`Master::sync_monitor_process`].
+    pub fn update(&mut self, raw: u32) -> Option<DcSyncEvent> {
+        if self.samples.len() < self.window {
+            self.samples.push(raw);
+        } else {
+            self.samples[self.next] = raw;
+        }
+        self.next = (self.next + 1) % self.window;
+
+        let (state, event) = if raw == SYNC_MON_LOST {
+            (DcSyncState::Lost, DcSyncEvent::Lost)
+        } else {
+            let diff = Duration::from_nanos(u64::from(raw));
+            if raw > self.threshold_ns {
+                (DcSyncState::OutOfSync, DcSyncEvent::OutOfSync { diff })
+            } else {
+                (DcSyncState::InSync, DcSyncEvent::InSync { diff })
+            }
+        };
+        let changed = state != self.state;
+        self.state = state;
+        if changed {
+            Some(event)
+        } else {
+            None
+        }
+    }
+
+    pub const fn state(&self) -> DcSyncState {
+        self.state
+    }
+
+    /// The most recent system time difference (`None` if lost or unknown).
+    pub fn last_diff(&self) -> Option<Duration> {
+        let last = (self.next + self.window - 1) % self.window;
+        self.samples.get(last).copied().and_then(sample_to_diff)
+    }
+
+    /// The maximum system time difference within the window
+    /// (`None` if no measurement was received).
+    pub fn max_diff(&self) -> Option<Duration> {
+        self.samples
+            .iter()
+            .filter(|&&raw| raw != SYNC_MON_LOST)
+            .max()
+            .map(|&raw| Duration::from_nanos(u64::from(raw)))
+    }
+
+    /// The recorded samples, oldest first (`None` for lost measurements).
+    pub fn history(&self) -> impl Iterator<Item = Option<Duration>> + '_ {
+        let (newer, older) = if self.samples.len() < self.window {
+            (&self.samples[..], &[][..])
+        } else {
+            self.samples.split_at(self.next)
+        };
+        older.iter().chain(newer).copied().map(sample_to_diff)
+    }
+}
+
+fn sample_to_diff(raw: u32) -> Option<Duration> {
+    if raw == SYNC_MON_LOST {
+        None
+    } else {
+        Some(Duration::from_nanos(u64::from(raw)))
+    }
+}
+
+/// `AssignActivate` word that enables the cyclic unit and SYNC0.
+pub const DC_ASSIGN_ACTIVATE_SYNC0: u16 = 0x0300;
+
+/// `AssignActivate` word that enables the cyclic unit, SYNC0 and SYNC1.
+pub const DC_ASSIGN_ACTIVATE_SYNC01: u16 = 0x0700;
+
+/// Cycle and shift time of a SYNC signal.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct DcSyncSignal {
+    pub cycle: Duration,
+    /// Shift time in nanoseconds; negative values fire the signal before
+    /// the cycle start.
+    pub shift: i32,
+}
+
+impl DcSyncSignal {
+    pub const fn new(cycle: Duration, shift: i32) -> Self {
+        Self { cycle, shift }
+    }
+}
+
+/// Distributed clock configuration of a slave.
+///
+/// The `assign_activate` word is device specific; it is listed in the
+/// `OpMode` entries of the ESI file (see `DcOpMode` with the `esi` feature).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DcConfig {
+    /// No SYNC signals, the slave runs synchronous to the frames.
+    FreeRun,
+    /// SYNC0 only.
+    Sync0 {
+        assign_activate: u16,
+        sync0: DcSyncSignal,
+    },
+    /// SYNC0 and SYNC1. The SYNC1 cycle is relative to SYNC0.
+    Sync01 {
+        assign_activate: u16,
+        sync0: DcSyncSignal,
+        sync1: DcSyncSignal,
+    },
+}
+
+impl DcConfig {
+    /// SYNC0 with the common `AssignActivate` word `0x0300`.
+    pub const fn sync0(cycle: Duration, shift: i32) -> Self {
+        Self::Sync0 {
+            assign_activate: DC_ASSIGN_ACTIVATE_SYNC0,
+            sync0: DcSyncSignal::new(cycle, shift),
+        }
+    }
+
+    /// SYNC0 and SYNC1 with the common `AssignActivate` word `0x0700`.
+    pub const fn sync01(sync0: DcSyncSignal, sync1: DcSyncSignal) -> Self {
+        Self::Sync01 {
+            assign_activate: DC_ASSIGN_ACTIVATE_SYNC01,
+            sync0,
+            sync1,
+        }
+    }
+
+    /// Replace the `AssignActivate` word (no effect in free run mode).
+    pub fn with_assign_activate(mut self, word: u16) -> Self {
+        match &mut self {
+            Self::FreeRun => {}
+            Self::Sync0 {
+                assign_activate, ..
+            }
+            | Self::Sync01 {
+                assign_activate, ..
+            } => *assign_activate = word,
+        }
+        self
+    }
+
+    pub const fn assign_activate(&self) -> u16 {
+        match self {
+            Self::FreeRun => 0,
+            Self::Sync0 {
+                assign_activate, ..
+            }
+            | Self::Sync01 {
+                assign_activate, ..
+            } => *assign_activate,
+        }
+    }
+
+    pub const fn sync0_signal(&self) -> Option<DcSyncSignal> {
+        match self {
+            Self::FreeRun => None,
+            Self::Sync0 { sync0, .. } | Self::Sync01 { sync0, .. } => Some(*sync0),
+        }
+    }
+
+    pub const fn sync1_signal(&self) -> Option<DcSyncSignal> {
+        match self {
+            Self::Sync01 { sync1, .. } => Some(*sync1),
+            _ => None,
+        }
+    }
+
+    /// Check the configuration against the cycle time of the master.
+    ///
+    /// The SYNC0 cycle must be a multiple or an integer fraction of the
+    /// master cycle, and the shift times must be shorter than their cycle
+    /// in either direction.
+    pub fn validate(&self, master_cycle: Duration) -> Result<()> {
+        let sync0 = match self.sync0_signal() {
+            None => return Ok(()),
+            Some(sync0) => sync0,
+        };
+        if self.assign_activate() == 0 {
+            return Err(Error::InvalidDcConfig("AssignActivate word is zero"));
+        }
+        let master_ns = master_cycle.as_nanos();
+        let sync0_ns = sync0.cycle.as_nanos();
+        if sync0_ns == 0 {
+            return Err(Error::InvalidDcConfig("SYNC0 cycle time is zero"));
+        }
+        if master_ns == 0 || !(master_ns % sync0_ns == 0 || sync0_ns % master_ns == 0) {
+            return Err(Error::InvalidDcConfig(
+                "SYNC0 cycle time does not match the master cycle",
+            ));
+        }
+        if u128::from(sync0.shift.unsigned_abs()) >= sync0_ns {
+            return Err(Error::InvalidDcConfig("SYNC0 shift time exceeds its cycle"));
+        }
+        if let Some(sync1) = self.sync1_signal() {
+            if !sync1.cycle.is_zero()
+                && u128::from(sync1.shift.unsigned_abs()) >= sync1.cycle.as_nanos()
+            {
+                return Err(Error::InvalidDcConfig("SYNC1 shift time exceeds its cycle"));
+            }
+        }
+        for signal in self.sync0_signal().iter().chain(&self.sync1_signal()) {
+            if u32::try_from(signal.cycle.as_nanos()).is_err() {
+                return Err(Error::InvalidDcConfig("cycle time out of range"));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn test_epoch_clock() {
+    let clock = EpochClock::now();
+    let now = rt::monotonic_ns();
+    // later than 2020-01-01
+    assert!(clock.at(now) > 631_152_000_000_000_000);
+    assert_eq!(clock.at(now + 1_000) - clock.at(now), 1_000);
+}
+
+#[test]
+fn test_drift_filter() {
+    let period = 1_000_000;
+    let mut filter = DriftFilter::default();
+    // first sample without offset does not start the filter
+    assert_eq!(filter.update(5_000_000, 5_000_000, period), 0);
+    assert_eq!(filter.update(6_000_100, 6_000_000, period), 0);
+    assert_eq!(filter.diff, 100);
+    // master is ahead: pull in by the sign of the difference
+    assert_eq!(filter.update(7_000_100, 7_000_000, period), 1);
+    // differences are normalised to ±period/2
+    filter.update(8_000_000, 7_100_000, period);
+    assert_eq!(filter.diff, -100_000);
+}
+
+#[test]
+fn test_dc_monitor() {
+    let ns = Duration::from_nanos;
+    let mut mon = DcMonitor::new(3, ns(100));
+    assert_eq!(mon.state(), DcSyncState::Unknown);
+    assert_eq!(mon.last_diff(), None);
+    assert_eq!(mon.update(20), Some(DcSyncEvent::InSync { diff: ns(20) }));
+    assert_eq!(mon.update(50), None);
+    assert_eq!(
+        mon.update(150),
+        Some(DcSyncEvent::OutOfSync { diff: ns(150) })
+    );
+    assert_eq!(mon.max_diff(), Some(ns(150)));
+    assert_eq!(mon.update(SYNC_MON_LOST), Some(DcSyncEvent::Lost));
+    assert_eq!(mon.state(), DcSyncState::Lost);
+    assert_eq!(mon.last_diff(), None);
+    assert_eq!(
+        mon.history().collect::<Vec<_>>(),
+        vec![Some(ns(50)), Some(ns(150)), None]
+    );
+    mon.update(10);
+    mon.update(10);
+    assert_eq!(mon.max_diff(), Some(ns(10)));
+    assert_eq!(mon.state(), DcSyncState::InSync);
+}
+
+#[test]
+fn test_dc_config() {
+    let us = Duration::from_micros;
+    let master = us(1_000);
+    assert!(DcConfig::FreeRun.validate(master).is_ok());
+    assert_eq!(DcConfig::FreeRun.assign_activate(), 0);
+    let cfg = DcConfig::sync0(us(1_000), 200_000);
+    assert!(cfg.validate(master).is_ok());
+    assert!(DcConfig::sync0(us(1_000), -200_000)
+        .validate(master)
+        .is_ok());
+    assert_eq!(cfg.assign_activate(), 0x0300);
+    assert!(DcConfig::sync0(us(2_000), 0).validate(master).is_ok());
+    assert!(DcConfig::sync0(us(250), 0).validate(master).is_ok());
+    assert!(DcConfig::sync0(us(300), 0).validate(master).is_err());
+    assert!(DcConfig::sync0(us(1_000), 1_000_000)
+        .validate(master)
+        .is_err());
+    assert!(DcConfig::sync0(us(1_000), -1_000_000)
+        .validate(master)
+        .is_err());
+    assert!(DcConfig::sync0(us(0), 0).validate(master).is_err());
+    let cfg = DcConfig::sync01(
+        DcSyncSignal::new(us(1_000), 0),
+        DcSyncSignal::new(us(500), 600_000),
+    );
+    assert!(cfg.validate(master).is_err());
+    let cfg = cfg.with_assign_activate(0x0730);
+    assert_eq!(cfg.assign_activate(), 0x0730);
+    assert_eq!(cfg.sync1_signal().unwrap().cycle, us(500));
+}
@@ -0,0 +1,305 @@
+// Part of ethercat-rs. Copyright 2018-2020 by the authors.
+// This work is dual-licensed under Apache 2.0 and MIT terms.
+
+//! Lookup of distributed clock operation modes in ESI files.
+
+use crate::{
+    dc::{DcConfig, DcSyncSignal, DC_ASSIGN_ACTIVATE_SYNC01},
+    types::*,
+};
+use serde::Deserialize;
+use std::{convert::TryFrom, time::Duration};
+
+/// Cycle time of a SYNC signal as given in an ESI `OpMode`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DcCycleTime {
+    /// A fixed time in nanoseconds (0 selects the base cycle).
+    Fixed(u32),
+    /// A multiple (positive) or a fraction (negative) of the base cycle,
+    /// which is the master cycle for SYNC0 and the SYNC0 cycle for SYNC1.
+    Factor(i32),
+}
+
+/// A distributed clock operation mode (`Dc/OpMode`) of an ESI device.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DcOpMode {
+    pub name: String,
+    pub description: Option<String>,
+    pub assign_activate: u16,
+    pub sync0_cycle: DcCycleTime,
+    /// Shift time of SYNC0 in nanoseconds.
+    pub sync0_shift: i32,
+    pub sync1_cycle: DcCycleTime,
+    /// Shift time of SYNC1 in nanoseconds.
+    pub sync1_shift: i32,
+}
+
+impl DcOpMode {
+    /// All DC operation modes of the device with the given product code
+    /// and revision in the ESI XML document.
+    pub fn from_esi(xml: &str, product_code: u32, revision: u32) -> Result<Vec<Self>> {
+        let info: EtherCatInfo =
+            serde_xml_rs::from_str(xml).map_err(|e| Error::Esi(e.to_string()))?;
+        for device in info.descriptions.devices.items {
+            if parse_int(&device.ty.product_code)? != i64::from(product_code) {
+                continue;
+            }
+            if let Some(rev) = &device.ty.revision {
+                if parse_int(rev)? != i64::from(revision) {
+                    continue;
+                }
+            }
+            return match device.dc {
+                None => Ok(vec![]),
+                Some(dc) => dc.op_modes.into_iter().map(Self::try_from).collect(),
+            };
+        }
+        Err(Error::Esi(format!(
+            "no device with product code {:#x} and revision {:#x}",
+            product_code, revision
+        )))
+    }
+
+    /// The DC configuration of this operation mode for the given master cycle.
+    pub fn dc_config(&self, master_cycle: Duration) -> Result<DcConfig> {
+        if self.assign_activate == 0 {
+            return Ok(DcConfig::FreeRun);
+        }
+        let sync0 = DcSyncSignal::new(cycle_time(self.sync0_cycle, master_cycle), self.sync0_shift);
+        if self.assign_activate & DC_ASSIGN_ACTIVATE_SYNC01 == DC_ASSIGN_ACTIVATE_SYNC01 {
+            let sync1 =
+                DcSyncSignal::new(cycle_time(self.sync1_cycle, sync0.cycle), self.sync1_shift);
+            Ok(DcConfig::sync01(sync0, sync1).with_assign_activate(self.assign_activate))
+        } else {
+            Ok(
+                DcConfig::sync0(sync0.cycle, sync0.shift)
+                    .with_assign_activate(self.assign_activate),
+            )
+        }
+    }
+}
+
+impl DcConfig {
+    /// Look up the DC configuration of a device in its ESI file.
+    ///
+    /// Without an `op_mode` name, the first synchronous operation mode
+    /// is chosen, or free run if the device has none.
+    pub fn from_esi(
+        xml: &str,
+        product_code: u32,
+        revision: u32,
+        op_mode: Option<&str>,
+        master_cycle: Duration,
+    ) -> Result<Self> {
+        let modes = DcOpMode::from_esi(xml, product_code, revision)?;
+        let mode = match op_mode {
+            Some(name) => modes
+                .iter()
+                .find(|m| m.name == name)
+                .ok_or_else(|| Error::Esi(format!("no DC operation mode {:?}", name)))?,
+            None => match modes.iter().find(|m| m.assign_activate != 0) {
+                Some(mode) => mode,
+                None => return Ok(DcConfig::FreeRun),
+            },
+        };
+        mode.dc_config(master_cycle)
+    }
+}
+
+fn cycle_time(cycle: DcCycleTime, base: Duration) -> Duration {
+    match cycle {
+        DcCycleTime::Fixed(0) => base,
+        DcCycleTime::Fixed(ns) => Duration::from_nanos(u64::from(ns)),
+        DcCycleTime::Factor(f) if f >= 0 => base * f as u32,
+        DcCycleTime::Factor(f) => base / f.unsigned_abs(),
+    }
+}
+
+/// Parse a decimal or hexadecimal (`#x...`) ESI number.
+fn parse_int(v: &str) -> Result<i64> {
+    let v = v.trim();
+    let res = match v.strip_prefix("#x") {
+        Some(hex) => i64::from_str_radix(hex, 16),
+        None => v.parse(),
+    };
+    res.map_err(|_| Error::Esi(format!("invalid number {:?}", v)))
+}
+
+impl TryFrom<OpMode> for DcOpMode {
+    type Error = Error;
+
+    fn try_from(m: OpMode) -> Result<Self> {
+        Ok(DcOpMode {
+            assign_activate: convert(Some(&m.assign_activate), "AssignActivate")?,
+            sync0_cycle: cycle(m.cycle_time_sync0, "CycleTimeSync0")?,
+            sync0_shift: convert(
+                m.shift_time_sync0.and_then(|t| t.value).as_deref(),
+                "ShiftTimeSync0",
+            )?,
+            sync1_cycle: cycle(m.cycle_time_sync1, "CycleTimeSync1")?,
+            sync1_shift: convert(
+                m.shift_time_sync1.and_then(|t| t.value).as_deref(),
+                "ShiftTimeSync1",
+            )?,
+            name: m.name,
+            description: m.desc,
+        })
+    }
+}
+
+/// Parse an optional ESI number (default 0) into the target type.
+fn convert<T: TryFrom<i64>>(v: Option<&str>, what: &str) -> Result<T> {
+    T::try_from(parse_int(v.unwrap_or("0"))?)
+        .map_err(|_| Error::Esi(format!("{} out of range", what)))
+}
+
+fn cycle(c: Option<CycleTime>, what: &str) -> Result<DcCycleTime> {
+    let c = c.unwrap_or_default();
+    match convert(c.factor.as_deref(), what)? {
+        0 => Ok(DcCycleTime::Fixed(convert(c.value.as_deref(), what)?)),
+        f => Ok(DcCycleTime::Factor(f)),
+    }
+}
+
+// The subset of the ESI schema that is needed to find the DC operation modes.
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct EtherCatInfo {
+    descriptions: Descriptions,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct Descriptions {
+    devices: Devices,
+}
+
+#[derive(Debug, Deserialize)]
+struct Devices {
+    #[serde(rename = "Device", default)]
+    items: Vec<Device>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct Device {
+    #[serde(rename = "Type")]
+    ty: DeviceType,
+    dc: Option<Dc>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct DeviceType {
+    product_code: String,
+    #[serde(rename = "RevisionNo")]
+    revision: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Dc {
+    #[serde(rename = "OpMode", default)]
+    op_modes: Vec<OpMode>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct OpMode {
+    name: String,
+    desc: Option<String>,
+    assign_activate: String,
+    cycle_time_sync0: Option<CycleTime>,
+    shift_time_sync0: Option<TimeValue>,
+    cycle_time_sync1: Option<CycleTime>,
+    shift_time_sync1: Option<TimeValue>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct CycleTime {
+    #[serde(rename = "Factor")]
+    factor: Option<String>,
+    #[serde(rename = "$value")]
+    value: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TimeValue {
+    #[serde(rename = "$value")]
+    value: Option<String>,
+}
+
+#[test]
+fn test_dc_op_modes() {
+    let xml = r##"
+<EtherCATInfo>
+  <Vendor><Id>2</Id></Vendor>
+  <Descriptions>
+    <Devices>
+      <Device Physics="YY">
+        <Type ProductCode="#x0bb93052" RevisionNo="#x00100000">EL3001</Type>
+        <Name>EL3001</Name>
+      </Device>
+      <Device Physics="YY">
+        <Type ProductCode="#x1b773052" RevisionNo="#x00100000">EL7031</Type>
+        <Name>EL7031</Name>
+        <Dc>
+          <OpMode>
+            <Name>DcOff</Name>
+            <Desc>FreeRun</Desc>
+            <AssignActivate>0</AssignActivate>
+          </OpMode>
+          <OpMode>
+            <Name>DcSync</Name>
+            <Desc>DC-Synchron</Desc>
+            <AssignActivate>#x300</AssignActivate>
+            <CycleTimeSync0 Factor="1">0</CycleTimeSync0>
+            <ShiftTimeSync0>0</ShiftTimeSync0>
+          </OpMode>
+          <OpMode>
+            <Name>DcSync01</Name>
+            <AssignActivate>#x700</AssignActivate>
+            <CycleTimeSync0 Factor="-2">0</CycleTimeSync0>
+            <ShiftTimeSync0>1000</ShiftTimeSync0>
+            <CycleTimeSync1 Factor="1">0</CycleTimeSync1>
+          </OpMode>
+        </Dc>
+      </Device>
+    </Devices>
+  </Descriptions>
+</EtherCATInfo>"##;
+    let us = Duration::from_micros;
+
+    let modes = DcOpMode::from_esi(xml, 0x1b77_3052, 0x0010_0000).unwrap();
+    assert_eq!(modes.len(), 3);
+    assert_eq!(modes[1].assign_activate, 0x300);
+    assert_eq!(modes[1].sync0_cycle, DcCycleTime::Factor(1));
+    assert_eq!(modes[1].description.as_deref(), Some("DC-Synchron"));
+
+    let cfg = DcConfig::from_esi(xml, 0x1b77_3052, 0x0010_0000, None, us(1_000)).unwrap();
+    assert_eq!(cfg, DcConfig::sync0(us(1_000), 0));
+    let cfg =
+        DcConfig::from_esi(xml, 0x1b77_3052, 0x0010_0000, Some("DcSync01"), us(1_000)).unwrap();
+    assert_eq!(
+        cfg,
+        DcConfig::sync01(
+            DcSyncSignal::new(us(500), 1_000),
+            DcSyncSignal::new(us(500), 0)
+        )
+    );
+    assert!(cfg.validate(us(1_000)).is_ok());
+
+    // negative shift times are passed on unchanged
+    let mode = DcOpMode {
+        sync0_shift: -2_000,
+        ..modes[1].clone()
+    };
+    assert_eq!(
+        mode.dc_config(us(1_000)).unwrap(),
+        DcConfig::sync0(us(1_000), -2_000)
+    );
+
+    let cfg = DcConfig::from_esi(xml, 0x0bb9_3052, 0x0010_0000, None, us(1_000)).unwrap();
+    assert_eq!(cfg, DcConfig::FreeRun);
+    assert!(DcOpMode::from_esi(xml, 0x1b77_3052, 0x0011_0000).is_err());
+}
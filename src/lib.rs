@@ -3,10 +3,59 @@
 
 use ethercat_sys as ec;
 
+mod al_status;
+#[cfg(feature = "tokio")]
+mod async_master;
+mod cyclic;
+mod dc;
+#[cfg(feature = "esi")]
+mod esi;
+mod link;
 mod master;
+mod mirror;
+mod observer;
+mod recovery;
+mod redundancy;
+mod rt;
+mod scan;
+mod schedule;
+#[cfg(feature = "serde")]
+mod serialize;
+mod stats;
+mod topology;
 mod types;
+mod verify;
+mod watch;
+mod wkc;
 
 pub use self::{
+    al_status::AlStatusCode,
+    cyclic::{
+        CycleError, CycleInfo, CyclicConfig, CyclicRunner, OverrunEvent, OverrunPolicy, SpawnError,
+    },
+    dc::{
+        DcConfig, DcController, DcMonitor, DcSyncEvent, DcSyncSignal, DcSyncState,
+        DC_ASSIGN_ACTIVATE_SYNC0, DC_ASSIGN_ACTIVATE_SYNC01,
+    },
+    link::{LinkAlert, LinkMonitor, LinkSample, LinkThresholds},
     master::{Domain, Master, MasterAccess, SlaveConfig},
+    mirror::{
+        OutputOverride, OutputOverrides, OverrideSender, ProcessImageMirror, ProcessImagePublisher,
+    },
+    observer::{ProcessDataEntry, ProcessDataObserver},
+    recovery::{RecoveryEvent, RecoverySupervisor},
+    redundancy::{RedundancyEvent, RedundancyMonitor, RedundancyState},
+    scan::{BusSnapshot, PdoEntrySnapshot, PdoSnapshot, SlaveSnapshot, SyncSnapshot},
+    schedule::DomainScheduler,
+    stats::{CycleStats, TimingStats, HISTOGRAM_BUCKETS},
+    topology::{OpenPort, Topology, TopologyLink, TopologyNode},
     types::*,
+    verify::{BusIssue, BusLayout, BusReport, ExpectedSlave},
+    watch::{StateEvent, StateEvents, StateWatcher},
+    wkc::{WkcEvent, WkcSupervisor},
 };
+
+#[cfg(feature = "tokio")]
+pub use self::async_master::AsyncMaster;
+#[cfg(feature = "esi")]
+pub use self::esi::{DcCycleTime, DcOpMode};
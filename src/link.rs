@@ -0,0 +1,246 @@
+// Part of ethercat-rs. Copyright 2018-2020 by the authors.
+// This work is dual-licensed under Apache 2.0 and MIT terms.
+
+use crate::{master::Master, types::*};
+use std::time::{Duration, Instant};
+
+/// Thresholds above which the [`LinkMonitor`] raises alerts.
+#[derive(Debug, Clone, Copy)]
+pub struct LinkThresholds {
+    /// Maximum ratio of lost to sent frames within one interval.
+    pub max_loss_ratio: f64,
+    /// Maximum number of lost frames within one interval.
+    pub max_lost_frames: u64,
+    /// Maximum number of transmission errors of a device within one interval.
+    pub max_tx_errors: u64,
+}
+
+impl Default for LinkThresholds {
+    fn default() -> Self {
+        Self {
+            max_loss_ratio: 0.001,
+            max_lost_frames: 10,
+            max_tx_errors: 0,
+        }
+    }
+}
+
+/// Frame statistics of one sampling interval.
+#[derive(Debug, Clone, Default)]
+pub struct LinkSample {
+    pub interval: Duration,
+    pub tx_frames: u64,
+    pub rx_frames: u64,
+    /// Frames that were sent, but not received again (e.g. timed out).
+    pub lost_frames: u64,
+    /// Transmission errors per device.
+    pub tx_errors: Vec<u64>,
+}
+
+impl LinkSample {
+    /// Ratio of lost to sent frames.
+    pub fn loss_ratio(&self) -> f64 {
+        if self.tx_frames == 0 {
+            0.
+        } else {
+            self.lost_frames as f64 / self.tx_frames as f64
+        }
+    }
+
+    /// Lost frames per second.
+    pub fn loss_rate(&self) -> f64 {
+        let secs = self.interval.as_secs_f64();
+        if secs > 0. {
+            self.lost_frames as f64 / secs
+        } else {
+            0.
+        }
+    }
+}
+
+/// An alert raised by the [`LinkMonitor`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum LinkAlert {
+    /// The link of a device went down.
+    LinkDown { device: usize },
+    /// The link of a device came back up.
+    LinkUp { device: usize },
+    /// Too many frames were lost within the interval.
+    FrameLoss { lost: u64, ratio: f64 },
+    /// A device reported too many transmission errors within the interval.
+    TxErrors { device: usize, count: u64 },
+}
+
+/// Monitors the link quality using the frame counters of the master.
+///
+/// Call [`LinkMonitor::sample`] periodically (e.g. every second); a
+/// read-only [`Master`] handle is sufficient, so this can run outside
+/// of the cyclic task. Each call compares the counters to the previous
+/// sample and returns the alerts for the elapsed interval.
+///
+/// Timed out frames show up as lost frames. The master does not export
+/// counters for unmatched datagrams; those are only reported in the
+/// kernel log.
+#[derive(Debug, Clone)]
+pub struct LinkMonitor {
+    thresholds: LinkThresholds,
+    last: Option<(Instant, MasterInfo)>,
+    last_sample: Option<LinkSample>,
+}
+
+impl LinkMonitor {
+    pub fn new(thresholds: LinkThresholds) -> Self {
+        Self {
+            thresholds,
+            last: None,
+            last_sample: None,
+        }
+    }
+
+    pub const fn thresholds(&self) -> &LinkThresholds {
+        &self.thresholds
+    }
+
+    /// Read the statistics of the master and evaluate them.
+    pub fn sample(&mut self, master: &Master) -> Result<Vec<LinkAlert>> {
+        let info = master.get_info()?;
+        Ok(self.update(info, Instant::now()))
+    }
+
+    /// Evaluate the statistics of the master taken at `now`.
+    pub fn update(&mut self, info: MasterInfo, now: Instant) -> Vec<LinkAlert> {
+        let mut alerts = vec![];
+        let (last_time, last) = match self.last.take() {
+            Some(last) => last,
+            None => {
+                // first sample: only report devices that are down
+                for (device, dev) in info.devices.iter().enumerate() {
+                    if !dev.link_up {
+                        alerts.push(LinkAlert::LinkDown { device });
+                    }
+                }
+                self.last = Some((now, info));
+                return alerts;
+            }
+        };
+
+        for (device, dev) in info.devices.iter().enumerate() {
+            let was_up = last.devices.get(device).map_or(true, |d| d.link_up);
+            if was_up && !dev.link_up {
+                alerts.push(LinkAlert::LinkDown { device });
+            } else if !was_up && dev.link_up {
+                alerts.push(LinkAlert::LinkUp { device });
+            }
+        }
+
+        // Counters that went backwards have been reset (e.g. by restarting
+        // the master); count from zero in that case.
+        let delta = |new: u64, old: u64| new.checked_sub(old).unwrap_or(new);
+        let tx_frames = delta(info.stats.tx_frames, last.stats.tx_frames);
+        let rx_frames = delta(info.stats.rx_frames, last.stats.rx_frames);
+        let sample = LinkSample {
+            interval: now.saturating_duration_since(last_time),
+            tx_frames,
+            rx_frames,
+            // Frames still in flight at the previous sample are received in
+            // this interval, so this evens out over time.
+            lost_frames: tx_frames.saturating_sub(rx_frames),
+            tx_errors: info
+                .devices
+                .iter()
+                .enumerate()
+                .map(|(i, dev)| {
+                    let old = last.devices.get(i).map_or(0, |d| d.stats.tx_errors);
+                    delta(dev.stats.tx_errors, old)
+                })
+                .collect(),
+        };
+
+        let ratio = sample.loss_ratio();
+        if sample.lost_frames > self.thresholds.max_lost_frames
+            || ratio > self.thresholds.max_loss_ratio
+        {
+            alerts.push(LinkAlert::FrameLoss {
+                lost: sample.lost_frames,
+                ratio,
+            });
+        }
+        for (device, &count) in sample.tx_errors.iter().enumerate() {
+            if count > self.thresholds.max_tx_errors {
+                alerts.push(LinkAlert::TxErrors { device, count });
+            }
+        }
+
+        self.last = Some((now, info));
+        self.last_sample = Some(sample);
+        alerts
+    }
+
+    /// Statistics of the last complete interval.
+    pub const fn last_sample(&self) -> Option<&LinkSample> {
+        self.last_sample.as_ref()
+    }
+}
+
+#[test]
+fn test_link_monitor() {
+    let info = |tx, rx, tx_errors, link_up| MasterInfo {
+        slave_count: 1,
+        config_count: 1,
+        domain_count: 1,
+        eoe_handler_count: 0,
+        phase: MasterPhase::Operation,
+        active: true,
+        link_up,
+        scan_busy: false,
+        devices: vec![MasterDeviceInfo {
+            mac_address: [0; 6],
+            attached: true,
+            link_up,
+            stats: FrameStats {
+                tx_frames: tx,
+                rx_frames: rx,
+                tx_errors,
+                ..FrameStats::default()
+            },
+        }],
+        stats: FrameStats {
+            tx_frames: tx,
+            rx_frames: rx,
+            ..FrameStats::default()
+        },
+        loss_rates: Rates::default(),
+        app_time: 0,
+        dc_ref_time: 0,
+        ref_clock: None,
+    };
+    let t0 = Instant::now();
+    let sec = Duration::from_secs(1);
+    let mut mon = LinkMonitor::new(LinkThresholds::default());
+    assert!(mon.update(info(100, 100, 0, true), t0).is_empty());
+    assert!(mon.last_sample().is_none());
+    assert!(mon
+        .update(info(20_100, 20_099, 0, true), t0 + sec)
+        .is_empty());
+    assert_eq!(mon.last_sample().unwrap().lost_frames, 1);
+    assert_eq!(
+        mon.update(info(20_200, 20_149, 1, false), t0 + 2 * sec),
+        vec![
+            LinkAlert::LinkDown { device: 0 },
+            LinkAlert::FrameLoss {
+                lost: 50,
+                ratio: 0.5
+            },
+            LinkAlert::TxErrors {
+                device: 0,
+                count: 1
+            },
+        ]
+    );
+    assert_eq!(mon.last_sample().unwrap().loss_rate(), 50.);
+    // counter reset
+    assert_eq!(
+        mon.update(info(1_000, 1_000, 0, true), t0 + 3 * sec),
+        vec![LinkAlert::LinkUp { device: 0 }]
+    );
+}
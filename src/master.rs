@@ -1,7 +1,16 @@
 // Part of ethercat-rs. Copyright 2018-2020 by the authors.
 // This work is dual-licensed under Apache 2.0 and MIT terms.
 
-use crate::{ec, types::*};
+use crate::{
+    al_status::{AlStatusCode, AL_STATUS_CODE_REG, AL_STATUS_ERROR, AL_STATUS_REG},
+    dc::DcConfig,
+    ec,
+    redundancy::RedundancyState,
+    scan::{BusSnapshot, PdoEntrySnapshot, PdoSnapshot, SlaveSnapshot, SyncSnapshot},
+    topology::Topology,
+    types::*,
+    verify::{BusLayout, BusReport},
+};
 use num_traits::cast::FromPrimitive;
 use std::{
     collections::HashMap,
@@ -10,6 +19,8 @@ use std::{
     fs::{File, OpenOptions},
     io,
     os::{raw::c_ulong, unix::io::AsRawFd},
+    thread,
+    time::{Duration, Instant},
 };
 
 macro_rules! ioctl {
@@ -20,6 +31,45 @@ macro_rules! ioctl {
     }}
 }
 
+/// Interval in which the AL status is polled while waiting for a state.
+const STATE_POLL_INTERVAL: Duration = Duration::from_millis(10);
+/// Time the master state machine gets to acknowledge an error indication
+/// that a slave already had when a state was requested.
+const STATE_ACK_TIMEOUT: Duration = Duration::from_millis(100);
+/// Register with the size of the process data RAM of a slave in KiB.
+const RAM_SIZE_REG: u16 = 0x0006;
+
+/// Progress of a slave towards a requested state.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum StateProgress {
+    Reached,
+    Pending,
+    Refused(AlStatusCode),
+}
+
+/// Evaluate the AL status of a slave that was requested to go to `to`.
+///
+/// `initial` is the error state and code of the slave when the request was
+/// made. The same error indication is only a refusal once the master had
+/// [`STATE_ACK_TIMEOUT`] to acknowledge it; a different one is a refusal
+/// right away.
+fn state_progress(
+    to: AlState,
+    initial: Option<(AlState, AlStatusCode)>,
+    current: AlState,
+    error: Option<AlStatusCode>,
+    since_request: Duration,
+) -> StateProgress {
+    match error {
+        Some(code) if initial != Some((current, code)) || since_request >= STATE_ACK_TIMEOUT => {
+            StateProgress::Refused(code)
+        }
+        Some(_) => StateProgress::Pending,
+        None if current == to => StateProgress::Reached,
+        None => StateProgress::Pending,
+    }
+}
+
 /// An EtherCAT master.
 pub struct Master {
     file: File,
@@ -174,21 +224,109 @@ impl Master {
     pub fn get_info(&self) -> Result<MasterInfo> {
         let mut data = ec::ec_ioctl_master_t::default();
         ioctl!(self, ec::ioctl::MASTER, &mut data)?;
-        let ec::ec_ioctl_master_t {
-            slave_count,
-            devices,
-            scan_busy,
-            app_time,
-            ..
-        } = data;
-        let first_device = devices.get(0).ok_or_else(|| Error::NoDevices)?;
-        let link_up = first_device.link_state != 0;
-        let scan_busy = scan_busy != 0;
+        // Frame and loss rates are passed in 1/1000 frames/s.
+        let frame_stats =
+            |tx_frames, rx_frames, tx_bytes, rx_bytes, tx_errors, rates: [_; 4]| FrameStats {
+                tx_frames,
+                rx_frames,
+                tx_bytes,
+                rx_bytes,
+                tx_errors,
+                tx_frame_rates: Rates::from_raw(rates[0], 1000.),
+                rx_frame_rates: Rates::from_raw(rates[1], 1000.),
+                tx_byte_rates: Rates::from_raw(rates[2], 1.),
+                rx_byte_rates: Rates::from_raw(rates[3], 1.),
+            };
+        let num_devices = (data.num_devices as usize).min(data.devices.len());
+        let devices: Vec<_> = data.devices[..num_devices]
+            .iter()
+            .map(|dev| MasterDeviceInfo {
+                mac_address: dev.address,
+                attached: dev.attached != 0,
+                link_up: dev.link_state != 0,
+                stats: frame_stats(
+                    dev.tx_count,
+                    dev.rx_count,
+                    dev.tx_bytes,
+                    dev.rx_bytes,
+                    dev.tx_errors,
+                    [
+                        dev.tx_frame_rates,
+                        dev.rx_frame_rates,
+                        dev.tx_byte_rates,
+                        dev.rx_byte_rates,
+                    ],
+                ),
+            })
+            .collect();
+        let link_up = devices.first().ok_or(Error::NoDevices)?.link_up;
+        let phase = match data.phase {
+            0 => MasterPhase::Orphaned,
+            1 => MasterPhase::Idle,
+            _ => MasterPhase::Operation,
+        };
         Ok(MasterInfo {
-            slave_count,
+            slave_count: data.slave_count,
+            config_count: data.config_count,
+            domain_count: data.domain_count,
+            eoe_handler_count: data.eoe_handler_count,
+            phase,
+            active: data.active != 0,
             link_up,
-            scan_busy,
-            app_time,
+            scan_busy: data.scan_busy != 0,
+            stats: frame_stats(
+                data.tx_count,
+                data.rx_count,
+                data.tx_bytes,
+                data.rx_bytes,
+                devices.iter().map(|d| d.stats.tx_errors).sum(),
+                [
+                    data.tx_frame_rates,
+                    data.rx_frame_rates,
+                    data.tx_byte_rates,
+                    data.rx_byte_rates,
+                ],
+            ),
+            devices,
+            loss_rates: Rates::from_raw(data.loss_rates, 1000.),
+            app_time: data.app_time,
+            dc_ref_time: data.dc_ref_time,
+            ref_clock: if data.ref_clock == 0xFFFF {
+                None
+            } else {
+                Some(SlavePos::from(data.ref_clock))
+            },
+        })
+    }
+
+    /// Number of Ethernet devices (main device and backup devices).
+    pub fn device_count(&self) -> Result<usize> {
+        Ok(self.get_info()?.devices.len())
+    }
+
+    /// Link states of all devices and whether the redundant path is in use
+    /// for any of the given domains.
+    ///
+    /// The domains must belong to this (requested) master.
+    pub fn redundancy_state(&self, domains: &[DomainIdx]) -> Result<RedundancyState> {
+        self.redundancy_state_of(self.device_count()?, domains)
+    }
+
+    pub(crate) fn redundancy_state_of(
+        &self,
+        device_count: usize,
+        domains: &[DomainIdx],
+    ) -> Result<RedundancyState> {
+        let devices = (0..device_count as u32)
+            .map(|idx| self.link_state(idx))
+            .collect::<Result<_>>()?;
+        let mut redundancy_active = false;
+        for &idx in domains {
+            redundancy_active |= self.domain(idx).state()?.redundancy_active;
+        }
+        Ok(RedundancyState {
+            devices,
+            redundancy_active,
         })
     }
 
@@ -203,7 +341,7 @@ impl Master {
                 ec::EC_PORT_NOT_CONFIGURED => SlavePortType::NotConfigured,
                 ec::EC_PORT_EBUS => SlavePortType::EBus,
                 ec::EC_PORT_MII => SlavePortType::MII,
-                x => panic!("invalid port type {}", x),
+                x => return Err(Error::InvalidPortType(x)),
             };
             port.link = SlavePortLink {
                 link_up: data.ports[i].link.link_up != 0,
@@ -237,9 +375,120 @@ impl Master {
             sync_count: data.sync_count,
             sdo_count: data.sdo_count,
             ports,
+            group: c_array_to_string(data.group.as_ptr()),
+            order: c_array_to_string(data.order.as_ptr()),
+            mailbox: SlaveMailboxInfo {
+                boot_rx: MailboxArea {
+                    offset: data.boot_rx_mailbox_offset,
+                    size: data.boot_rx_mailbox_size,
+                },
+                boot_tx: MailboxArea {
+                    offset: data.boot_tx_mailbox_offset,
+                    size: data.boot_tx_mailbox_size,
+                },
+                std_rx: MailboxArea {
+                    offset: data.std_rx_mailbox_offset,
+                    size: data.std_rx_mailbox_size,
+                },
+                std_tx: MailboxArea {
+                    offset: data.std_tx_mailbox_offset,
+                    size: data.std_tx_mailbox_size,
+                },
+                protocols: MailboxProtocols::from(data.mailbox_protocols),
+            },
+            coe: CoeDetails {
+                enable_sdo: data.coe_details.enable_sdo() != 0,
+                enable_sdo_info: data.coe_details.enable_sdo_info() != 0,
+                enable_pdo_assign: data.coe_details.enable_pdo_assign() != 0,
+                enable_pdo_configuration: data.coe_details.enable_pdo_configuration() != 0,
+                enable_upload_at_startup: data.coe_details.enable_upload_at_startup() != 0,
+                enable_sdo_complete_access: data.coe_details.enable_sdo_complete_access() != 0,
+            },
+            has_general_category: data.has_general_category != 0,
+            enable_safeop: data.general_flags.enable_safeop() != 0,
+            enable_not_lrw: data.general_flags.enable_not_lrw() != 0,
+            fmmu_bit: data.fmmu_bit != 0,
+            dc: SlaveDcInfo {
+                supported: data.dc_supported != 0,
+                range: if data.dc_range == ec::EC_DC_64 {
+                    DcRange::Bits64
+                } else {
+                    DcRange::Bits32
+                },
+                has_system_time: data.has_dc_system_time != 0,
+                transmission_delay: data.transmission_delay,
+            },
+            sii_size: data.sii_nwords as usize * 2,
+        })
+    }
+
+    /// Read the information about the master, all slaves with their sync
+    /// managers, PDOs and PDO entries, and all slave configurations.
+    pub fn scan(&mut self) -> Result<BusSnapshot> {
+        let master = self.get_info()?;
+        let mut slaves = Vec::with_capacity(master.slave_count as usize);
+        for pos in 0..master.slave_count {
+            let pos = SlavePos::from(pos as u16);
+            let info = self.get_slave_info(pos)?;
+            let mut syncs = Vec::with_capacity(info.sync_count as usize);
+            for sm in 0..info.sync_count {
+                let sm = self.get_sync(pos, SmIdx::from(sm))?;
+                let mut pdos = Vec::with_capacity(sm.pdo_count as usize);
+                for pdo_pos in 0..sm.pdo_count {
+                    let pdo_pos = PdoPos::from(pdo_pos);
+                    let pdo = self.get_pdo(pos, sm.idx, pdo_pos)?;
+                    let entries = (0..pdo.entry_count)
+                        .map(|e| {
+                            let e =
+                                self.get_pdo_entry(pos, sm.idx, pdo_pos, PdoEntryPos::from(e))?;
+                            Ok(PdoEntrySnapshot {
+                                index: u16::from(e.entry_idx.idx),
+                                subindex: u8::from(e.entry_idx.sub_idx),
+                                bit_len: e.bit_len,
+                                name: e.name,
+                            })
+                        })
+                        .collect::<Result<_>>()?;
+                    pdos.push(PdoSnapshot {
+                        index: u16::from(pdo.idx),
+                        name: pdo.name,
+                        entries,
+                    });
+                }
+                syncs.push(SyncSnapshot { info: sm, pdos });
+            }
+            slaves.push(SlaveSnapshot { info, syncs });
+        }
+        let configs = (0..master.config_count)
+            .map(|idx| self.get_config_info(idx))
+            .collect::<Result<_>>()?;
+        Ok(BusSnapshot {
+            master,
+            slaves,
+            configs,
         })
     }
 
+    /// Compare the slaves on the bus with the expected layout.
+    ///
+    /// Use this before activating the master, e.g. with
+    /// `master.verify_bus(&layout)?.into_result()?`.
+    pub fn verify_bus(&self, layout: &BusLayout) -> Result<BusReport> {
+        let slaves = (0..self.get_info()?.slave_count)
+            .map(|pos| self.get_slave_info(SlavePos::from(pos as u16)))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(layout.verify(&slaves))
+    }
+
+    /// Physical topology of the bus, built from the port information
+    /// of all slaves.
+    pub fn topology(&self) -> Result<Topology> {
+        let slaves = (0..self.get_info()?.slave_count)
+            .map(|pos| self.get_slave_info(SlavePos::from(pos as u16)))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Topology::from_slaves(&slaves))
+    }
+
     pub fn get_config_info(&self, idx: SlaveConfigIdx) -> Result<ConfigInfo> {
         let mut data = ec::ec_ioctl_config_t::default();
         data.config_index = idx;
@@ -253,17 +502,207 @@ impl Master {
         } else {
             Some(SlavePos::from(data.slave_position as u16))
         };
+        let mut syncs = vec![];
+        for (sm, sync) in data.syncs.iter().enumerate() {
+            let direction = match sync.dir {
+                ec::EC_DIR_OUTPUT => SyncDirection::Output,
+                ec::EC_DIR_INPUT => SyncDirection::Input,
+                _ => SyncDirection::Invalid,
+            };
+            // PDOs can be assigned without configuring the sync manager
+            if sync.pdo_count == 0 && direction == SyncDirection::Invalid {
+                continue;
+            }
+            let watchdog_mode = match sync.watchdog_mode {
+                ec::EC_WD_ENABLE => WatchdogMode::Enable,
+                ec::EC_WD_DISABLE => WatchdogMode::Disable,
+                _ => WatchdogMode::Default,
+            };
+            let pdos = (0..sync.pdo_count as u16)
+                .map(|pos| self.get_config_pdo(idx, sm as u8, pos))
+                .collect::<Result<_>>()?;
+            syncs.push(ConfigSmInfo {
+                idx: SmIdx::from(sm as u8),
+                direction,
+                watchdog_mode,
+                pdos,
+            });
+        }
+        let sdos = (0..data.sdo_count)
+            .map(|pos| self.get_config_sdo(idx, pos))
+            .collect::<Result<_>>()?;
+        let idns = (0..data.idn_count)
+            .map(|pos| self.get_config_idn(idx, pos))
+            .collect::<Result<_>>()?;
+        let sync_signal = |s: &ec::ec_sync_signal_t| ConfigSyncSignal {
+            cycle_time: s.cycle_time,
+            shift_time: s.shift_time,
+        };
         Ok(ConfigInfo {
             alias: data.alias,
             position: data.position,
             id,
             slave_position,
-            sdo_count: data.sdo_count,
-            idn_count: data.idn_count,
+            syncs,
+            watchdog_divider: data.watchdog_divider,
+            watchdog_intervals: data.watchdog_intervals,
+            sdos,
+            idns,
+            dc: ConfigDcInfo {
+                assign_activate: data.dc_assign_activate,
+                sync: [sync_signal(&data.dc_sync[0]), sync_signal(&data.dc_sync[1])],
+            },
+        })
+    }
+
+    fn get_config_pdo(
+        &self,
+        idx: SlaveConfigIdx,
+        sync_index: u8,
+        pdo_pos: u16,
+    ) -> Result<ConfigPdoInfo> {
+        let mut pdo = ec::ec_ioctl_config_pdo_t::default();
+        pdo.config_index = idx;
+        pdo.sync_index = sync_index;
+        pdo.pdo_pos = pdo_pos;
+        ioctl!(self, ec::ioctl::CONFIG_PDO, &mut pdo)?;
+        let entries = (0..pdo.entry_count)
+            .map(|entry_pos| {
+                let mut entry = ec::ec_ioctl_config_pdo_entry_t::default();
+                entry.config_index = idx;
+                entry.sync_index = sync_index;
+                entry.pdo_pos = pdo_pos;
+                entry.entry_pos = entry_pos;
+                ioctl!(self, ec::ioctl::CONFIG_PDO_ENTRY, &mut entry)?;
+                Ok(ConfigPdoEntryInfo {
+                    index: entry.index,
+                    subindex: entry.subindex,
+                    bit_len: entry.bit_length,
+                    name: c_array_to_string(entry.name.as_ptr()),
+                })
+            })
+            .collect::<Result<_>>()?;
+        Ok(ConfigPdoInfo {
+            index: pdo.index,
+            name: c_array_to_string(pdo.name.as_ptr()),
+            entries,
+        })
+    }
+
+    fn get_config_sdo(&self, idx: SlaveConfigIdx, sdo_pos: u32) -> Result<ConfigSdoInfo> {
+        let mut sdo = ec::ec_ioctl_config_sdo_t::default();
+        sdo.config_index = idx;
+        sdo.sdo_pos = sdo_pos;
+        ioctl!(self, ec::ioctl::CONFIG_SDO, &mut sdo)?;
+        let size = (sdo.size as usize).min(sdo.data.len());
+        Ok(ConfigSdoInfo {
+            index: sdo.index,
+            subindex: sdo.subindex,
+            complete_access: sdo.complete_access != 0,
+            data: sdo.data[..size].to_vec(),
+        })
+    }
+
+    fn get_config_idn(&self, idx: SlaveConfigIdx, idn_pos: u32) -> Result<ConfigIdnInfo> {
+        let mut idn = ec::ec_ioctl_config_idn_t::default();
+        idn.config_index = idx;
+        idn.idn_pos = idn_pos;
+        ioctl!(self, ec::ioctl::CONFIG_IDN, &mut idn)?;
+        let size = (idn.size as usize).min(idn.data.len());
+        Ok(ConfigIdnInfo {
+            drive_no: idn.drive_no,
+            idn: idn.idn,
+            state: AlState::try_from(idn.state as u8)
+                .map_err(|_| Error::InvalidAlState(idn.state as u8))?,
+            data: idn.data[..size].to_vec(),
+        })
+    }
+
+    pub fn get_domain_info(&self, idx: DomainIdx) -> Result<DomainInfo> {
+        let domain_index = u32::try_from(idx).map_err(|_| Error::DomainIdx(usize::from(idx)))?;
+        let mut data = ec::ec_ioctl_domain_t::default();
+        data.index = domain_index;
+        ioctl!(self, ec::ioctl::DOMAIN, &mut data)?;
+        let device_count = self.device_count()?;
+        let fmmus = (0..data.fmmu_count)
+            .map(|fmmu_index| {
+                let mut fmmu = ec::ec_ioctl_domain_fmmu_t::default();
+                fmmu.domain_index = domain_index;
+                fmmu.fmmu_index = fmmu_index;
+                ioctl!(self, ec::ioctl::DOMAIN_FMMU, &mut fmmu)?;
+                let direction = match fmmu.dir {
+                    ec::EC_DIR_OUTPUT => SyncDirection::Output,
+                    ec::EC_DIR_INPUT => SyncDirection::Input,
+                    _ => SyncDirection::Invalid,
+                };
+                Ok(DomainFmmuInfo {
+                    slave_config_alias: fmmu.slave_config_alias,
+                    slave_config_position: fmmu.slave_config_position,
+                    sync_index: SmIdx::from(fmmu.sync_index),
+                    direction,
+                    logical_address: fmmu.logical_address,
+                    offset: fmmu.logical_address.wrapping_sub(data.logical_base_address) as usize,
+                    data_size: fmmu.data_size as usize,
+                })
+            })
+            .collect::<Result<_>>()?;
+        Ok(DomainInfo {
+            data_size: data.data_size as usize,
+            logical_base_address: data.logical_base_address,
+            working_counters: data
+                .working_counter
+                .iter()
+                .take(device_count)
+                .copied()
+                .collect(),
+            expected_working_counter: data.expected_working_counter,
+            fmmus,
         })
     }
 
-    pub fn configure_slave(&mut self, addr: SlaveAddr, expected: SlaveId) -> Result<SlaveConfig> {
+    /// Copy the process data of a domain, which also works without the
+    /// memory mapping of an activated master (e.g. for a read-only master).
+    ///
+    /// The size of `data` must match the domain's data size.
+    pub fn read_domain_data(&self, idx: DomainIdx, data: &mut [u8]) -> Result<()> {
+        let mut req = ec::ec_ioctl_domain_data_t::default();
+        req.domain_index = u32::try_from(idx).map_err(|_| Error::DomainIdx(usize::from(idx)))?;
+        req.data_size = data.len() as u32;
+        req.target = data.as_mut_ptr();
+        ioctl!(self, ec::ioctl::DOMAIN_DATA, &mut req).map(|_| ())
+    }
+
+    /// Slave configs of the domain that contribute to its working counter,
+    /// but whose slave is missing, not operational or has an error.
+    ///
+    /// This narrows down an incomplete working counter to the slaves
+    /// that are responsible for it.
+    pub fn wc_suspects(&self, idx: DomainIdx) -> Result<Vec<WcContribution>> {
+        let contributions = self.get_domain_info(idx)?.wc_contributions();
+        let configs = (0..self.get_info()?.config_count)
+            .map(|i| self.get_config_info(i))
+            .collect::<Result<Vec<_>>>()?;
+        let mut suspects = vec![];
+        for c in contributions {
+            let slave_position = configs
+                .iter()
+                .find(|cfg| (cfg.alias, cfg.position) == (c.alias, c.position))
+                .and_then(|cfg| cfg.slave_position);
+            let ok = match slave_position {
+                Some(pos) => {
+                    let slave = self.get_slave_info(pos)?;
+                    slave.al_state == AlState::Op && slave.error_flag == 0
+                }
+                None => false,
+            };
+            if !ok {
+                suspects.push(c);
+            }
+        }
+        Ok(suspects)
+    }
+
+    pub fn configure_slave(&self, addr: SlaveAddr, expected: SlaveId) -> Result<SlaveConfig> {
         log::debug!("Configure slave {:?}", addr);
         let mut data = ec::ec_ioctl_config_t::default();
         let (alias, pos) = addr.as_pair();
@@ -273,11 +712,16 @@ impl Master {
         data.product_code = expected.product_code;
         ioctl!(self, ec::ioctl::CREATE_SLAVE_CONFIG, &mut data)?;
         Ok(SlaveConfig {
-            master: self,
             idx: data.config_index,
         })
     }
 
+    /// Access a slave configuration that was created before
+    /// by [`Master::configure_slave`].
+    pub const fn slave_config(&self, idx: SlaveConfigIdx) -> SlaveConfig {
+        SlaveConfig { idx }
+    }
+
     pub fn get_sdo(&mut self, slave_pos: SlavePos, sdo_pos: SdoPos) -> Result<SdoInfo> {
         let mut sdo = ec::ec_ioctl_slave_sdo_t::default();
         sdo.slave_position = u16::from(slave_pos);
@@ -477,6 +921,131 @@ impl Master {
         Ok(())
     }
 
+    /// Request a state for a slave and wait until the slave has reached it.
+    ///
+    /// If the slave refuses the transition, its AL status code is returned
+    /// in [`Error::StateRefused`]. An error the slave already indicated
+    /// before the request (e.g. SAFEOP+ERR after a watchdog) is left to the
+    /// master to acknowledge first.
+    pub fn request_state_and_wait(
+        &mut self,
+        slave_pos: SlavePos,
+        state: AlState,
+        timeout: Duration,
+    ) -> Result<()> {
+        self.request_states_and_wait(&[slave_pos], state, timeout)
+    }
+
+    /// Request a state for all slaves and wait until all have reached it.
+    pub fn request_state_all_and_wait(&mut self, state: AlState, timeout: Duration) -> Result<()> {
+        let slaves: Vec<_> = (0..self.get_info()?.slave_count)
+            .map(|pos| SlavePos::from(pos as u16))
+            .collect();
+        self.request_states_and_wait(&slaves, state, timeout)
+    }
+
+    fn request_states_and_wait(
+        &mut self,
+        slaves: &[SlavePos],
+        state: AlState,
+        timeout: Duration,
+    ) -> Result<()> {
+        let start = Instant::now();
+        let mut pending = vec![];
+        for &pos in slaves {
+            let (from, error) = self.al_status(pos)?;
+            // an error the slave already has is acknowledged by the master
+            // while it processes the request
+            let initial = if error {
+                Some((from, self.al_status_code(pos)?))
+            } else {
+                None
+            };
+            self.request_state(pos, state)?;
+            pending.push((pos, from, initial));
+        }
+        loop {
+            let mut i = 0;
+            while i < pending.len() {
+                let (pos, from, initial) = pending[i];
+                let (current, error) = self.al_status(pos)?;
+                let code = if error {
+                    Some(self.al_status_code(pos)?)
+                } else {
+                    None
+                };
+                match state_progress(state, initial, current, code, start.elapsed()) {
+                    StateProgress::Refused(code) => {
+                        return Err(Error::StateRefused {
+                            position: u16::from(pos),
+                            from,
+                            to: state,
+                            code,
+                        });
+                    }
+                    StateProgress::Reached => {
+                        pending.remove(i);
+                    }
+                    StateProgress::Pending => i += 1,
+                }
+            }
+            let (pos, _, _) = match pending.first() {
+                None => return Ok(()),
+                Some(p) => *p,
+            };
+            if start.elapsed() >= timeout {
+                let (current, _) = self.al_status(pos)?;
+                let code = self.al_status_code(pos)?;
+                return Err(Error::StateTimeout {
+                    position: u16::from(pos),
+                    to: state,
+                    current,
+                    code: Some(code).filter(|c| *c != AlStatusCode::NO_ERROR),
+                });
+            }
+            thread::sleep(STATE_POLL_INTERVAL);
+        }
+    }
+
+    /// Current AL state of a slave and whether its error indication is set.
+    fn al_status(&mut self, slave_pos: SlavePos) -> Result<(AlState, bool)> {
+        let mut status = [0; 2];
+        self.read_register(slave_pos, AL_STATUS_REG, &mut status)?;
+        let state = status[0] & 0x0F;
+        let state = AlState::try_from(state).map_err(|_| Error::InvalidAlState(state))?;
+        Ok((state, status[0] & AL_STATUS_ERROR != 0))
+    }
+
+    /// Read the AL status code of a slave (register 0x0134).
+    pub fn al_status_code(&mut self, slave_pos: SlavePos) -> Result<AlStatusCode> {
+        let mut code = [0; 2];
+        self.read_register(slave_pos, AL_STATUS_CODE_REG, &mut code)?;
+        Ok(AlStatusCode(u16::from_le_bytes(code)))
+    }
+
+    /// Size of the process data RAM of a slave in bytes (register 0x0006).
+    pub fn slave_ram_size(&mut self, slave_pos: SlavePos) -> Result<u32> {
+        let mut size = [0; 1];
+        self.read_register(slave_pos, RAM_SIZE_REG, &mut size)?;
+        Ok(u32::from(size[0]) * 1024)
+    }
+
+    /// Read registers of a slave, starting at `address`.
+    pub fn read_register(
+        &mut self,
+        slave_pos: SlavePos,
+        address: u16,
+        data: &mut [u8],
+    ) -> Result<()> {
+        let mut reg = ec::ec_ioctl_slave_reg_t::default();
+        reg.slave_position = u16::from(slave_pos);
+        reg.address = address;
+        reg.size = data.len() as u64;
+        reg.data = data.as_mut_ptr();
+        ioctl!(self, ec::ioctl::SLAVE_REG_READ, &mut reg)?;
+        Ok(())
+    }
+
     #[cfg(feature = "sncn")]
     pub fn dict_upload(&mut self, slave_pos: SlavePos) -> Result<()> {
         let mut data = ec::ec_ioctl_slave_dict_upload_t::default();
@@ -500,11 +1069,15 @@ impl Master {
         Ok(())
     }
 
+    /// Queue a datagram that reads the system time difference of all DC slaves.
     pub fn sync_monitor_queue(&mut self) -> Result<()> {
         ioctl!(self, ec::ioctl::SYNC_MON_QUEUE)?;
         Ok(())
     }
 
+    /// Upper estimation of the maximum system time difference in nanoseconds,
+    /// or `0xFFFFFFFF` if the datagram queued by [`Master::sync_monitor_queue`]
+    /// was not received. See [`DcMonitor`](crate::DcMonitor) for an evaluation over time.
     pub fn sync_monitor_process(&mut self) -> Result<u32> {
         let mut time = 0;
         ioctl!(self, ec::ioctl::SYNC_MON_PROCESS, &mut time)?;
@@ -517,6 +1090,41 @@ impl Master {
         Ok(time)
     }
 
+    /// Select the slave whose clock is used as DC reference clock.
+    ///
+    /// `None` restores the default, the first DC capable slave.
+    /// Must be called before activating the master.
+    pub fn select_reference_clock(&self, idx: Option<SlaveConfigIdx>) -> Result<()> {
+        let config_index = idx.unwrap_or(u32::MAX);
+        // The kernel takes the config index as the ioctl argument itself.
+        ioctl!(
+            self,
+            ec::ioctl::SELECT_REF_CLOCK,
+            config_index as usize as *const u32
+        )?;
+        Ok(())
+    }
+
+    /// Queue a datagram that reads the 64-bit system time of the reference clock.
+    #[cfg(ref_clk_time_64)]
+    pub fn reference_clock_time_64_queue(&mut self) -> Result<()> {
+        ioctl!(self, ec::ioctl::REF_CLK_TIME_QUEUE_64)?;
+        Ok(())
+    }
+
+    /// 64-bit system time of the reference clock in nanoseconds since 2000-01-01,
+    /// as read by the datagram queued with
+    /// [`Master::reference_clock_time_64_queue`].
+    ///
+    /// Unlike [`Master::get_reference_clock_time`], the value does not
+    /// wrap around after about 4.3 seconds.
+    #[cfg(ref_clk_time_64)]
+    pub fn reference_clock_time_64(&mut self) -> Result<u64> {
+        let mut time = 0;
+        ioctl!(self, ec::ioctl::REF_CLK_TIME_64, &mut time)?;
+        Ok(time)
+    }
+
     // XXX missing: write_idn, read_idn
 }
 
@@ -539,65 +1147,129 @@ fn test_c_array_to_string() {
     assert_eq!(c_array_to_string(arr.as_ptr()), "Product code");
 }
 
-pub struct SlaveConfig<'m> {
-    master: &'m Master,
+#[test]
+fn test_state_progress() {
+    use AlState::*;
+    let watchdog = AlStatusCode(0x001B);
+    let ms = Duration::from_millis;
+
+    // a slave without error is refused as soon as it indicates one
+    assert_eq!(
+        state_progress(Op, None, SafeOp, None, ms(0)),
+        StateProgress::Pending
+    );
+    assert_eq!(
+        state_progress(Op, None, SafeOp, Some(watchdog), ms(0)),
+        StateProgress::Refused(watchdog)
+    );
+    assert_eq!(
+        state_progress(Op, None, Op, None, ms(20)),
+        StateProgress::Reached
+    );
+
+    // a slave in SAFEOP+ERR keeps its error until the master acknowledged it
+    let initial = Some((SafeOp, watchdog));
+    assert_eq!(
+        state_progress(Op, initial, SafeOp, Some(watchdog), ms(10)),
+        StateProgress::Pending
+    );
+    assert_eq!(
+        state_progress(Op, initial, SafeOp, None, ms(20)),
+        StateProgress::Pending
+    );
+    assert_eq!(
+        state_progress(Op, initial, Op, None, ms(30)),
+        StateProgress::Reached
+    );
+    // a new error is a refusal right away
+    let sync = AlStatusCode(0x001A);
+    assert_eq!(
+        state_progress(Op, initial, SafeOp, Some(sync), ms(10)),
+        StateProgress::Refused(sync)
+    );
+    // the old error is a refusal if it was not acknowledged in time
+    assert_eq!(
+        state_progress(Op, initial, SafeOp, Some(watchdog), STATE_ACK_TIMEOUT),
+        StateProgress::Refused(watchdog)
+    );
+}
+
+/// Handle of a slave configuration created by [`Master::configure_slave`].
+///
+/// The handle only holds the [`SlaveConfigIdx`], so any number of them can
+/// be kept while the master is used otherwise; the master is passed to
+/// every method.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SlaveConfig {
     idx: SlaveConfigIdx,
 }
 
-impl<'m> SlaveConfig<'m> {
+impl SlaveConfig {
     pub const fn index(&self) -> SlaveConfigIdx {
         self.idx
     }
 
-    pub fn state(&self) -> Result<SlaveConfigState> {
+    pub fn state(&self, master: &Master) -> Result<SlaveConfigState> {
         let mut state = ec::ec_slave_config_state_t::default();
         let mut data = ec::ec_ioctl_sc_state_t {
             config_index: self.idx,
             state: &mut state,
         };
-        ioctl!(self.master, ec::ioctl::SC_STATE, &mut data)?;
+        ioctl!(master, ec::ioctl::SC_STATE, &mut data)?;
         let al_state_u8 = state.al_state() as u8;
+        let online = state.online() != 0;
         Ok(SlaveConfigState {
-            online: state.online() != 0,
+            online,
             operational: state.operational() != 0,
-            al_state: AlState::try_from(al_state_u8)
-                .map_err(|_| Error::InvalidAlState(al_state_u8))?,
+            al_state: if online {
+                Some(
+                    AlState::try_from(al_state_u8)
+                        .map_err(|_| Error::InvalidAlState(al_state_u8))?,
+                )
+            } else {
+                None
+            },
         })
     }
 
     /// Configure PDOs of a specifc Sync Manager
-    pub fn config_sm_pdos(&mut self, sm_cfg: SmCfg, pdo_cfgs: &[PdoCfg]) -> Result<()> {
-        self.config_sync_manager(&sm_cfg)?;
-        self.clear_pdo_assignments(sm_cfg.idx)?;
+    pub fn config_sm_pdos(
+        &self,
+        master: &Master,
+        sm_cfg: SmCfg,
+        pdo_cfgs: &[PdoCfg],
+    ) -> Result<()> {
+        self.config_sync_manager(master, &sm_cfg)?;
+        self.clear_pdo_assignments(master, sm_cfg.idx)?;
         for pdo_cfg in &*pdo_cfgs {
-            self.add_pdo_assignment(sm_cfg.idx, pdo_cfg.idx)?;
+            self.add_pdo_assignment(master, sm_cfg.idx, pdo_cfg.idx)?;
             if !pdo_cfg.entries.is_empty() {
-                self.clear_pdo_mapping(pdo_cfg.idx)?;
+                self.clear_pdo_mapping(master, pdo_cfg.idx)?;
                 for entry in &pdo_cfg.entries {
-                    self.add_pdo_mapping(pdo_cfg.idx, entry)?;
+                    self.add_pdo_mapping(master, pdo_cfg.idx, entry)?;
                 }
             }
         }
         Ok(())
     }
 
-    pub fn config_watchdog(&mut self, divider: u16, intervals: u16) -> Result<()> {
+    pub fn config_watchdog(&self, master: &Master, divider: u16, intervals: u16) -> Result<()> {
         let mut data = ec::ec_ioctl_config_t::default();
         data.config_index = self.idx;
         data.watchdog_divider = divider;
         data.watchdog_intervals = intervals;
-        ioctl!(self.master, ec::ioctl::SC_WATCHDOG, &data).map(|_| ())
+        ioctl!(master, ec::ioctl::SC_WATCHDOG, &data).map(|_| ())
     }
 
     #[cfg(feature = "sncn")]
-    pub fn config_overlapping_pdos(&mut self, allow: bool) -> Result<()> {
+    pub fn config_overlapping_pdos(&self, master: &Master, allow: bool) -> Result<()> {
         let mut data = ec::ec_ioctl_config_t::default();
         data.config_index = self.idx;
         data.allow_overlapping_pdos = allow as u8;
-        ioctl!(self.master, ec::ioctl::SC_OVERLAPPING_IO, &data).map(|_| ())
+        ioctl!(master, ec::ioctl::SC_OVERLAPPING_IO, &data).map(|_| ())
     }
 
-    pub fn config_sync_manager(&mut self, cfg: &SmCfg) -> Result<()> {
+    pub fn config_sync_manager(&self, master: &Master, cfg: &SmCfg) -> Result<()> {
         log::debug!("Configure Sync Manager: {:?}", cfg);
         if u8::from(cfg.idx) >= ec::EC_MAX_SYNC_MANAGERS as u8 {
             return Err(Error::SmIdxTooLarge);
@@ -608,32 +1280,42 @@ impl<'m> SlaveConfig<'m> {
         data.syncs[ix].dir = cfg.direction as u32;
         data.syncs[ix].watchdog_mode = cfg.watchdog_mode as u32;
         data.syncs[ix].config_this = 1;
-        ioctl!(self.master, ec::ioctl::SC_SYNC, &data).map(|_| ())
+        ioctl!(master, ec::ioctl::SC_SYNC, &data).map(|_| ())
     }
 
-    pub fn clear_pdo_assignments(&mut self, sync_idx: SmIdx) -> Result<()> {
+    pub fn clear_pdo_assignments(&self, master: &Master, sync_idx: SmIdx) -> Result<()> {
         let mut data = ec::ec_ioctl_config_pdo_t::default();
         data.config_index = self.idx;
         data.sync_index = u8::from(sync_idx);
-        ioctl!(self.master, ec::ioctl::SC_CLEAR_PDOS, &data).map(|_| ())
+        ioctl!(master, ec::ioctl::SC_CLEAR_PDOS, &data).map(|_| ())
     }
 
-    pub fn add_pdo_assignment(&mut self, sync_idx: SmIdx, pdo_idx: PdoIdx) -> Result<()> {
+    pub fn add_pdo_assignment(
+        &self,
+        master: &Master,
+        sync_idx: SmIdx,
+        pdo_idx: PdoIdx,
+    ) -> Result<()> {
         let mut data = ec::ec_ioctl_config_pdo_t::default();
         data.config_index = self.idx;
         data.sync_index = u8::from(sync_idx);
         data.index = u16::from(pdo_idx);
-        ioctl!(self.master, ec::ioctl::SC_ADD_PDO, &data).map(|_| ())
+        ioctl!(master, ec::ioctl::SC_ADD_PDO, &data).map(|_| ())
     }
 
-    pub fn clear_pdo_mapping(&mut self, pdo_idx: PdoIdx) -> Result<()> {
+    pub fn clear_pdo_mapping(&self, master: &Master, pdo_idx: PdoIdx) -> Result<()> {
         let mut data = ec::ec_ioctl_config_pdo_t::default();
         data.config_index = self.idx;
         data.index = u16::from(pdo_idx);
-        ioctl!(self.master, ec::ioctl::SC_CLEAR_ENTRIES, &data).map(|_| ())
+        ioctl!(master, ec::ioctl::SC_CLEAR_ENTRIES, &data).map(|_| ())
     }
 
-    pub fn add_pdo_mapping(&mut self, pdo_index: PdoIdx, entry: &PdoEntryInfo) -> Result<()> {
+    pub fn add_pdo_mapping(
+        &self,
+        master: &Master,
+        pdo_index: PdoIdx,
+        entry: &PdoEntryInfo,
+    ) -> Result<()> {
         let data = ec::ec_ioctl_add_pdo_entry_t {
             config_index: self.idx,
             pdo_index: u16::from(pdo_index),
@@ -641,10 +1323,15 @@ impl<'m> SlaveConfig<'m> {
             entry_subindex: u8::from(entry.entry_idx.sub_idx),
             entry_bit_length: entry.bit_len,
         };
-        ioctl!(self.master, ec::ioctl::SC_ADD_ENTRY, &data).map(|_| ())
+        ioctl!(master, ec::ioctl::SC_ADD_ENTRY, &data).map(|_| ())
     }
 
-    pub fn register_pdo_entry(&mut self, index: PdoEntryIdx, domain: DomainIdx) -> Result<Offset> {
+    pub fn register_pdo_entry(
+        &self,
+        master: &Master,
+        index: PdoEntryIdx,
+        domain: DomainIdx,
+    ) -> Result<Offset> {
         let mut data = ec::ec_ioctl_reg_pdo_entry_t {
             config_index: self.idx,
             entry_index: u16::from(index.idx),
@@ -653,7 +1340,7 @@ impl<'m> SlaveConfig<'m> {
                 .map_err(|_| Error::DomainIdx(usize::from(domain)))?,
             bit_position: 0,
         };
-        let byte = ioctl!(self.master, ec::ioctl::SC_REG_PDO_ENTRY, &mut data)?;
+        let byte = ioctl!(master, ec::ioctl::SC_REG_PDO_ENTRY, &mut data)?;
         Ok(Offset {
             byte: byte as usize,
             bit: data.bit_position,
@@ -661,7 +1348,8 @@ impl<'m> SlaveConfig<'m> {
     }
 
     pub fn register_pdo_entry_by_position(
-        &mut self,
+        &self,
+        master: &Master,
         sync_index: SmIdx,
         pdo_pos: u32,
         entry_pos: u32,
@@ -676,7 +1364,7 @@ impl<'m> SlaveConfig<'m> {
                 .map_err(|_| Error::DomainIdx(usize::from(domain)))?,
             bit_position: 0,
         };
-        let byte = ioctl!(self.master, ec::ioctl::SC_REG_PDO_POS, &mut data)?;
+        let byte = ioctl!(master, ec::ioctl::SC_REG_PDO_POS, &mut data)?;
         Ok(Offset {
             byte: byte as usize,
             bit: data.bit_position,
@@ -684,7 +1372,8 @@ impl<'m> SlaveConfig<'m> {
     }
 
     pub fn config_dc(
-        &mut self,
+        &self,
+        master: &Master,
         assign_activate: u16,
         sync0_cycle_time: u32,
         sync0_shift_time: i32,
@@ -698,10 +1387,26 @@ impl<'m> SlaveConfig<'m> {
         data.dc_sync[0].shift_time = sync0_shift_time;
         data.dc_sync[1].cycle_time = sync1_cycle_time;
         data.dc_sync[1].shift_time = sync1_shift_time;
-        ioctl!(self.master, ec::ioctl::SC_DC, &data).map(|_| ())
+        ioctl!(master, ec::ioctl::SC_DC, &data).map(|_| ())
+    }
+
+    /// Configure the distributed clock of the slave from a [`DcConfig`],
+    /// after validating it against the master cycle.
+    pub fn set_dc(&self, master: &Master, cfg: &DcConfig, master_cycle: Duration) -> Result<()> {
+        cfg.validate(master_cycle)?;
+        let sync0 = cfg.sync0_signal().unwrap_or_default();
+        let sync1 = cfg.sync1_signal().unwrap_or_default();
+        self.config_dc(
+            master,
+            cfg.assign_activate(),
+            sync0.cycle.as_nanos() as u32,
+            sync0.shift,
+            sync1.cycle.as_nanos() as u32,
+            sync1.shift,
+        )
     }
 
-    pub fn add_sdo<T>(&mut self, index: SdoIdx, data: &T) -> Result<()>
+    pub fn add_sdo<T>(&self, master: &Master, index: SdoIdx, data: &T) -> Result<()>
     where
         T: SdoData + ?Sized,
     {
@@ -713,10 +1418,10 @@ impl<'m> SlaveConfig<'m> {
             size: data.data_size() as u64,
             complete_access: 0,
         };
-        ioctl!(self.master, ec::ioctl::SC_SDO, &data).map(|_| ())
+        ioctl!(master, ec::ioctl::SC_SDO, &data).map(|_| ())
     }
 
-    pub fn add_complete_sdo(&mut self, index: SdoIdx, data: &[u8]) -> Result<()> {
+    pub fn add_complete_sdo(&self, master: &Master, index: SdoIdx, data: &[u8]) -> Result<()> {
         let data = ec::ec_ioctl_sc_sdo_t {
             config_index: self.idx,
             index: u16::from(index.idx),
@@ -725,11 +1430,12 @@ impl<'m> SlaveConfig<'m> {
             size: data.len() as u64,
             complete_access: 1,
         };
-        ioctl!(self.master, ec::ioctl::SC_SDO, &data).map(|_| ())
+        ioctl!(master, ec::ioctl::SC_SDO, &data).map(|_| ())
     }
 
     pub fn config_idn(
-        &mut self,
+        &self,
+        master: &Master,
         drive_no: u8,
         idn: u16,
         al_state: AlState,
@@ -743,33 +1449,33 @@ impl<'m> SlaveConfig<'m> {
             data: data.as_ptr(),
             size: data.len() as u64,
         };
-        ioctl!(self.master, ec::ioctl::SC_IDN, &data).map(|_| ())
+        ioctl!(master, ec::ioctl::SC_IDN, &data).map(|_| ())
     }
 
-    pub fn set_emerg_size(&mut self, elements: u64) -> Result<()> {
+    pub fn set_emerg_size(&self, master: &Master, elements: u64) -> Result<()> {
         let mut data = ec::ec_ioctl_sc_emerg_t::default();
         data.config_index = self.idx;
         data.size = elements;
-        ioctl!(self.master, ec::ioctl::SC_EMERG_SIZE, &data).map(|_| ())
+        ioctl!(master, ec::ioctl::SC_EMERG_SIZE, &data).map(|_| ())
     }
 
-    pub fn pop_emerg(&mut self, target: &mut [u8]) -> Result<()> {
+    pub fn pop_emerg(&self, master: &Master, target: &mut [u8]) -> Result<()> {
         let mut data = ec::ec_ioctl_sc_emerg_t::default();
         data.config_index = self.idx;
         data.target = target.as_mut_ptr();
-        ioctl!(self.master, ec::ioctl::SC_EMERG_POP, &mut data).map(|_| ())
+        ioctl!(master, ec::ioctl::SC_EMERG_POP, &mut data).map(|_| ())
     }
 
-    pub fn clear_emerg(&mut self) -> Result<()> {
+    pub fn clear_emerg(&self, master: &Master) -> Result<()> {
         let mut data = ec::ec_ioctl_sc_emerg_t::default();
         data.config_index = self.idx;
-        ioctl!(self.master, ec::ioctl::SC_EMERG_CLEAR, &data).map(|_| ())
+        ioctl!(master, ec::ioctl::SC_EMERG_CLEAR, &data).map(|_| ())
     }
 
-    pub fn emerg_overruns(&mut self) -> Result<i32> {
+    pub fn emerg_overruns(&self, master: &Master) -> Result<i32> {
         let mut data = ec::ec_ioctl_sc_emerg_t::default();
         data.config_index = self.idx;
-        ioctl!(self.master, ec::ioctl::SC_EMERG_OVERRUNS, &mut data)?;
+        ioctl!(master, ec::ioctl::SC_EMERG_OVERRUNS, &mut data)?;
         Ok(data.overruns)
     }
 
@@ -790,6 +1496,10 @@ impl<'m> Domain<'m> {
         .map(|v| v as usize)
     }
 
+    pub fn info(&self) -> Result<DomainInfo> {
+        self.master.get_domain_info(self.idx)
+    }
+
     pub fn state(&self) -> Result<DomainState> {
         let mut state = ec::ec_domain_state_t::default();
         let mut data = ec::ec_ioctl_domain_state_t {
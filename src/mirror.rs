@@ -0,0 +1,292 @@
+// Part of ethercat-rs. Copyright 2018-2020 by the authors.
+// This work is dual-licensed under Apache 2.0 and MIT terms.
+
+use crate::types::*;
+use std::{
+    hint,
+    sync::{
+        atomic::{fence, AtomicU64, AtomicU8, Ordering},
+        mpsc, Arc,
+    },
+};
+
+/// Image shared between publisher and readers, protected by a sequence
+/// counter that is odd while the publisher is writing.
+#[derive(Debug)]
+struct Shared {
+    seq: AtomicU64,
+    cycle: AtomicU64,
+    data: Box<[AtomicU8]>,
+}
+
+/// Writing end of a [`ProcessImageMirror`], used by the cyclic task.
+///
+/// Publishing is wait-free and does not allocate.
+#[derive(Debug)]
+pub struct ProcessImagePublisher {
+    shared: Arc<Shared>,
+}
+
+/// Consistent copy of a process image for non real-time threads, e.g. an
+/// HMI or a logger.
+///
+/// The cyclic task publishes the image with a [`ProcessImagePublisher`];
+/// the mirror can be cloned for any number of readers.
+#[derive(Debug, Clone)]
+pub struct ProcessImageMirror {
+    shared: Arc<Shared>,
+}
+
+impl ProcessImagePublisher {
+    pub fn len(&self) -> usize {
+        self.shared.data.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.shared.data.is_empty()
+    }
+
+    /// Publish the process data of a cycle; `data` must have the size of the mirror.
+    pub fn publish(&mut self, data: &[u8], cycle: u64) {
+        let shared = &*self.shared;
+        let seq = shared.seq.load(Ordering::Relaxed);
+        shared.seq.store(seq.wrapping_add(1), Ordering::Relaxed);
+        fence(Ordering::Release);
+        for (dst, src) in shared.data.iter().zip(data) {
+            dst.store(*src, Ordering::Relaxed);
+        }
+        shared.cycle.store(cycle, Ordering::Relaxed);
+        shared.seq.store(seq.wrapping_add(2), Ordering::Release);
+    }
+}
+
+impl ProcessImageMirror {
+    /// Create a mirror of a process image with `size` bytes and its publisher.
+    pub fn new(size: usize) -> (ProcessImagePublisher, Self) {
+        let shared = Arc::new(Shared {
+            seq: AtomicU64::new(0),
+            cycle: AtomicU64::new(0),
+            data: (0..size).map(|_| AtomicU8::new(0)).collect(),
+        });
+        (
+            ProcessImagePublisher {
+                shared: shared.clone(),
+            },
+            Self { shared },
+        )
+    }
+
+    pub fn len(&self) -> usize {
+        self.shared.data.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.shared.data.is_empty()
+    }
+
+    /// Copy a consistent image into `buf` and return the cycle it was
+    /// published in (0 if nothing was published yet).
+    ///
+    /// Retries while the publisher is writing.
+    pub fn read(&self, buf: &mut [u8]) -> u64 {
+        let shared = &*self.shared;
+        loop {
+            let seq = shared.seq.load(Ordering::Acquire);
+            if seq % 2 == 1 {
+                hint::spin_loop();
+                continue;
+            }
+            for (dst, src) in buf.iter_mut().zip(shared.data.iter()) {
+                *dst = src.load(Ordering::Relaxed);
+            }
+            let cycle = shared.cycle.load(Ordering::Relaxed);
+            fence(Ordering::Acquire);
+            if shared.seq.load(Ordering::Relaxed) == seq {
+                return cycle;
+            }
+        }
+    }
+
+    /// A copy of the current image and its cycle.
+    pub fn snapshot(&self) -> (Vec<u8>, u64) {
+        let mut data = vec![0; self.len()];
+        let cycle = self.read(&mut data);
+        (data, cycle)
+    }
+}
+
+/// A request from a non real-time thread to force outputs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputOverride {
+    /// Force the entry at `offset` to `value` until it is cleared.
+    Set {
+        offset: Offset,
+        bit_len: u8,
+        value: u64,
+    },
+    /// Stop forcing the entry at `offset`.
+    Clear {
+        offset: Offset,
+        bit_len: u8,
+    },
+    ClearAll,
+}
+
+/// Sends [`OutputOverride`]s to the cyclic task.
+#[derive(Debug, Clone)]
+pub struct OverrideSender {
+    tx: mpsc::SyncSender<OutputOverride>,
+}
+
+impl OverrideSender {
+    /// Queue a request, waiting if the queue is full.
+    ///
+    /// Returns `false` if the cyclic end was dropped.
+    pub fn send(&self, request: OutputOverride) -> bool {
+        self.tx.send(request).is_ok()
+    }
+}
+
+/// Cyclic end of the output override channel.
+///
+/// The forced values are kept in preallocated tables, so applying them
+/// does not allocate.
+#[derive(Debug)]
+pub struct OutputOverrides {
+    rx: mpsc::Receiver<OutputOverride>,
+    value: Vec<u8>,
+    mask: Vec<u8>,
+    active: bool,
+}
+
+impl OutputOverrides {
+    /// Create the override tables for a domain of `size` bytes and a sender
+    /// with room for `capacity` pending requests.
+    pub fn new(size: usize, capacity: usize) -> (Self, OverrideSender) {
+        let (tx, rx) = mpsc::sync_channel(capacity);
+        (
+            Self {
+                rx,
+                value: vec![0; size],
+                mask: vec![0; size],
+                active: false,
+            },
+            OverrideSender { tx },
+        )
+    }
+
+    /// Any output is forced.
+    pub fn is_active(&self) -> bool {
+        self.active
+    }
+
+    /// Take the pending requests and force the outputs in `data`.
+    ///
+    /// Call this in the cyclic task after the application wrote its outputs
+    /// and before the domain is queued.
+    pub fn apply(&mut self, data: &mut [u8]) {
+        let mut changed = false;
+        while let Ok(request) = self.rx.try_recv() {
+            changed = true;
+            match request {
+                OutputOverride::Set {
+                    offset,
+                    bit_len,
+                    value,
+                } => {
+                    write_bits(&mut self.value, offset, bit_len, value);
+                    write_bits(&mut self.mask, offset, bit_len, u64::MAX);
+                }
+                OutputOverride::Clear { offset, bit_len } => {
+                    write_bits(&mut self.mask, offset, bit_len, 0);
+                }
+                OutputOverride::ClearAll => self.mask.iter_mut().for_each(|m| *m = 0),
+            }
+        }
+        if changed {
+            self.active = self.mask.iter().any(|m| *m != 0);
+        }
+        if self.active {
+            for ((d, v), m) in data.iter_mut().zip(&self.value).zip(&self.mask) {
+                *d = (*d & !m) | (v & m);
+            }
+        }
+    }
+}
+
+/// Write the lowest `bit_len` bits of `value` at `offset`, ignoring bits
+/// outside of `data`.
+fn write_bits(data: &mut [u8], offset: Offset, bit_len: u8, value: u64) {
+    let start = offset.byte * 8 + offset.bit as usize;
+    for i in 0..usize::from(bit_len.min(64)) {
+        let bit = start + i;
+        if let Some(byte) = data.get_mut(bit / 8) {
+            let mask = 1 << (bit % 8);
+            if value >> i & 1 == 1 {
+                *byte |= mask;
+            } else {
+                *byte &= !mask;
+            }
+        }
+    }
+}
+
+#[test]
+fn test_process_image_mirror() {
+    let (mut publisher, mirror) = ProcessImageMirror::new(4);
+    assert_eq!(mirror.snapshot(), (vec![0; 4], 0));
+    publisher.publish(&[1, 2, 3, 4], 7);
+    let reader = mirror.clone();
+    assert_eq!(reader.snapshot(), (vec![1, 2, 3, 4], 7));
+
+    // readers always see a complete image
+    let handle = std::thread::spawn(move || {
+        let mut buf = [0; 4];
+        for _ in 0..10_000 {
+            reader.read(&mut buf);
+            assert!(buf.iter().all(|b| *b == buf[0]));
+        }
+    });
+    for i in 0..10_000_u64 {
+        publisher.publish(&[i as u8; 4], i);
+    }
+    handle.join().unwrap();
+}
+
+#[test]
+fn test_output_overrides() {
+    let (mut overrides, sender) = OutputOverrides::new(3, 8);
+    let mut data = [0xFF, 0x00, 0x0F];
+    overrides.apply(&mut data);
+    assert!(!overrides.is_active());
+    assert_eq!(data, [0xFF, 0x00, 0x0F]);
+
+    assert!(sender.send(OutputOverride::Set {
+        offset: Offset { byte: 0, bit: 4 },
+        bit_len: 4,
+        value: 0b0101,
+    }));
+    assert!(sender.send(OutputOverride::Set {
+        offset: Offset { byte: 1, bit: 0 },
+        bit_len: 16,
+        value: 0xABCD,
+    }));
+    overrides.apply(&mut data);
+    assert!(overrides.is_active());
+    assert_eq!(data, [0x5F, 0xCD, 0xAB]);
+
+    // the override stays in effect when the application writes again
+    let mut data = [0x00, 0x00, 0x00];
+    sender.send(OutputOverride::Clear {
+        offset: Offset { byte: 1, bit: 0 },
+        bit_len: 16,
+    });
+    overrides.apply(&mut data);
+    assert_eq!(data, [0x50, 0x00, 0x00]);
+
+    sender.send(OutputOverride::ClearAll);
+    let mut data = [0x00, 0x00, 0x00];
+    overrides.apply(&mut data);
+    assert!(!overrides.is_active());
+    assert_eq!(data, [0x00, 0x00, 0x00]);
+}
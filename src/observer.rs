@@ -0,0 +1,211 @@
+// Part of ethercat-rs. Copyright 2018-2020 by the authors.
+// This work is dual-licensed under Apache 2.0 and MIT terms.
+
+use crate::{master::Master, types::*};
+
+/// A PDO entry in the process data of a domain.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProcessDataEntry {
+    pub slave_config_alias: u16,
+    pub slave_config_position: u16,
+    pub direction: SyncDirection,
+    pub pdo_index: u16,
+    pub index: u16,
+    pub subindex: u8,
+    pub bit_len: u8,
+    pub name: String,
+    pub offset: Offset,
+}
+
+/// Watches the process data of a domain without being the application that
+/// requested the master, e.g. from a monitoring tool running next to the
+/// real-time application.
+///
+/// The data is copied by the master on request, so this also works with a
+/// [`MasterAccess::ReadOnly`](crate::MasterAccess::ReadOnly) master and does
+/// not need the memory mapping created by [`Master::activate`].
+///
+/// The layout is calculated from the FMMUs of the domain and the configured
+/// PDO mapping, i.e. it contains all mapped entries, not only the ones
+/// registered by the application.
+#[derive(Debug, Clone)]
+pub struct ProcessDataObserver {
+    domain: DomainIdx,
+    entries: Vec<ProcessDataEntry>,
+    data: Vec<u8>,
+}
+
+impl ProcessDataObserver {
+    /// Read the layout of the domain.
+    pub fn new(master: &Master, domain: DomainIdx) -> Result<Self> {
+        let info = master.get_domain_info(domain)?;
+        let configs = (0..master.get_info()?.config_count)
+            .map(|i| master.get_config_info(i))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self {
+            domain,
+            entries: layout(&info, &configs),
+            data: vec![0; info.data_size],
+        })
+    }
+
+    pub const fn domain(&self) -> DomainIdx {
+        self.domain
+    }
+
+    pub fn entries(&self) -> &[ProcessDataEntry] {
+        &self.entries
+    }
+
+    pub fn entry(&self, index: u16, subindex: u8) -> Option<&ProcessDataEntry> {
+        self.entries
+            .iter()
+            .find(|e| e.index == index && e.subindex == subindex)
+    }
+
+    /// Fetch a new copy of the process data.
+    pub fn update(&mut self, master: &Master) -> Result<()> {
+        master.read_domain_data(self.domain, &mut self.data)
+    }
+
+    /// The process data as of the last update.
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// The raw value of an entry (up to 64 bit), little endian as on the bus.
+    pub fn value(&self, entry: &ProcessDataEntry) -> Option<u64> {
+        read_bits(&self.data, entry.offset, entry.bit_len)
+    }
+
+    /// The data of a byte aligned entry.
+    pub fn bytes(&self, entry: &ProcessDataEntry) -> Option<&[u8]> {
+        if entry.offset.bit != 0 || entry.bit_len % 8 != 0 {
+            return None;
+        }
+        let start = entry.offset.byte;
+        self.data.get(start..start + usize::from(entry.bit_len / 8))
+    }
+}
+
+/// Place the PDO entries of the configs into the FMMUs of the domain.
+fn layout(info: &DomainInfo, configs: &[ConfigInfo]) -> Vec<ProcessDataEntry> {
+    let mut entries = vec![];
+    for fmmu in &info.fmmus {
+        let config = configs.iter().find(|c| {
+            (c.alias, c.position) == (fmmu.slave_config_alias, fmmu.slave_config_position)
+        });
+        let sync = match config.and_then(|c| c.syncs.iter().find(|s| s.idx == fmmu.sync_index)) {
+            Some(sync) => sync,
+            None => continue,
+        };
+        let mut bit = fmmu.offset * 8;
+        for pdo in &sync.pdos {
+            for entry in &pdo.entries {
+                // index 0 marks a gap in the mapping
+                if entry.index != 0 {
+                    entries.push(ProcessDataEntry {
+                        slave_config_alias: fmmu.slave_config_alias,
+                        slave_config_position: fmmu.slave_config_position,
+                        direction: fmmu.direction,
+                        pdo_index: pdo.index,
+                        index: entry.index,
+                        subindex: entry.subindex,
+                        bit_len: entry.bit_len,
+                        name: entry.name.clone(),
+                        offset: Offset {
+                            byte: bit / 8,
+                            bit: (bit % 8) as u32,
+                        },
+                    });
+                }
+                bit += usize::from(entry.bit_len);
+            }
+        }
+    }
+    entries
+}
+
+fn read_bits(data: &[u8], offset: Offset, bit_len: u8) -> Option<u64> {
+    if bit_len > 64 {
+        return None;
+    }
+    let start = offset.bit as usize;
+    let end = start + usize::from(bit_len);
+    let bytes = data.get(offset.byte..offset.byte + (end + 7) / 8)?;
+    let mut value = 0_u128;
+    for (i, b) in bytes.iter().enumerate() {
+        value |= u128::from(*b) << (8 * i);
+    }
+    let mask = (1_u128 << bit_len) - 1;
+    Some(((value >> start) & mask) as u64)
+}
+
+#[test]
+fn test_process_data_layout() {
+    let entry = |index, subindex, bit_len| ConfigPdoEntryInfo {
+        index,
+        subindex,
+        bit_len,
+        name: String::new(),
+    };
+    let config = ConfigInfo {
+        alias: 0,
+        position: 1,
+        id: SlaveId::new(2, 0x1000),
+        slave_position: None,
+        syncs: vec![ConfigSmInfo {
+            idx: SmIdx::from(3),
+            direction: SyncDirection::Input,
+            watchdog_mode: WatchdogMode::Default,
+            pdos: vec![ConfigPdoInfo {
+                index: 0x1a00,
+                name: String::new(),
+                entries: vec![
+                    entry(0x6000, 1, 1),
+                    entry(0, 0, 3),
+                    entry(0x6000, 2, 4),
+                    entry(0x6010, 1, 16),
+                ],
+            }],
+        }],
+        watchdog_divider: 0,
+        watchdog_intervals: 0,
+        sdos: vec![],
+        idns: vec![],
+        dc: ConfigDcInfo::default(),
+    };
+    let info = DomainInfo {
+        data_size: 5,
+        logical_base_address: 0x100,
+        working_counters: vec![0],
+        expected_working_counter: 1,
+        fmmus: vec![DomainFmmuInfo {
+            slave_config_alias: 0,
+            slave_config_position: 1,
+            sync_index: SmIdx::from(3),
+            direction: SyncDirection::Input,
+            logical_address: 0x102,
+            offset: 2,
+            data_size: 3,
+        }],
+    };
+    let obs = ProcessDataObserver {
+        domain: DomainIdx::from(0),
+        entries: layout(&info, &[config]),
+        data: vec![0, 0, 0b1010_0001, 0x34, 0x12],
+    };
+    let offsets: Vec<_> = obs
+        .entries()
+        .iter()
+        .map(|e| (e.offset.byte, e.offset.bit))
+        .collect();
+    assert_eq!(offsets, vec![(2, 0), (2, 4), (3, 0)]);
+    let e = |sub| obs.entry(0x6000, sub).unwrap();
+    assert_eq!(obs.value(e(1)), Some(1));
+    assert_eq!(obs.value(e(2)), Some(0b1010));
+    let word = obs.entry(0x6010, 1).unwrap();
+    assert_eq!(obs.value(word), Some(0x1234));
+    assert_eq!(obs.bytes(word), Some(&[0x34, 0x12][..]));
+    assert_eq!(obs.bytes(e(2)), None);
+}
@@ -0,0 +1,244 @@
+// Part of ethercat-rs. Copyright 2018-2020 by the authors.
+// This work is dual-licensed under Apache 2.0 and MIT terms.
+
+use crate::{master::Master, types::*};
+use std::time::{Duration, Instant};
+
+type RecoveryHook = Box<dyn FnMut(&mut Master, SlaveConfigIdx) -> Result<()> + Send>;
+
+/// Progress of the slaves as seen by the [`RecoverySupervisor`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RecoveryEvent {
+    /// The slave is no longer on the bus (e.g. power loss or link interruption).
+    SlaveOffline { config: SlaveConfigIdx },
+    /// The slave is on the bus, but has left OP (e.g. sync manager watchdog).
+    SlaveNotOperational {
+        config: SlaveConfigIdx,
+        al_state: AlState,
+    },
+    /// A lost slave is being brought back to OP by the master.
+    Reconfiguring {
+        config: SlaveConfigIdx,
+        al_state: Option<AlState>,
+    },
+    /// A lost slave is operational again.
+    SlaveRecovered {
+        config: SlaveConfigIdx,
+        downtime: Duration,
+    },
+    /// The number of slaves responding to the master has changed.
+    RespondingChanged { from: u32, to: u32 },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum SlaveStatus {
+    /// The slave was not operational yet.
+    Starting,
+    Operational,
+    Lost {
+        since: Instant,
+        al_state: Option<AlState>,
+    },
+}
+
+/// Watches the configured slaves and reports when they drop out of OP and
+/// when they return.
+///
+/// The master reconfigures slaves that come back on the bus by itself, so
+/// the supervisor only reports the progress. Once a slave is operational
+/// again, the recovery hook is invoked, e.g. to re-home the axes of a drive.
+///
+/// Call [`RecoverySupervisor::check`] from the cyclic task (not necessarily
+/// in every cycle); slaves are only supervised after they were operational
+/// once.
+pub struct RecoverySupervisor {
+    configs: Vec<(SlaveConfigIdx, SlaveStatus)>,
+    slaves_responding: Option<u32>,
+    on_recovered: Option<RecoveryHook>,
+}
+
+impl RecoverySupervisor {
+    pub fn new(configs: Vec<SlaveConfigIdx>) -> Self {
+        Self {
+            configs: configs
+                .into_iter()
+                .map(|c| (c, SlaveStatus::Starting))
+                .collect(),
+            slaves_responding: None,
+            on_recovered: None,
+        }
+    }
+
+    /// Set the hook that is invoked for every slave that is operational again.
+    pub fn with_recovery_hook<F>(mut self, f: F) -> Self
+    where
+        F: FnMut(&mut Master, SlaveConfigIdx) -> Result<()> + Send + 'static,
+    {
+        self.on_recovered = Some(Box::new(f));
+        self
+    }
+
+    /// Slaves that dropped out of OP and did not return yet.
+    pub fn lost(&self) -> impl Iterator<Item = SlaveConfigIdx> + '_ {
+        self.configs
+            .iter()
+            .filter(|(_, s)| matches!(s, SlaveStatus::Lost { .. }))
+            .map(|(c, _)| *c)
+    }
+
+    /// All supervised slaves are operational.
+    pub fn all_operational(&self) -> bool {
+        self.configs
+            .iter()
+            .all(|(_, s)| *s == SlaveStatus::Operational)
+    }
+
+    /// Read the slave states and run the recovery hook for recovered slaves.
+    pub fn check(&mut self, master: &mut Master) -> Result<Vec<RecoveryEvent>> {
+        let master_state = master.state()?;
+        let states = self
+            .configs
+            .iter()
+            .map(|(idx, _)| master.slave_config(*idx).state(master))
+            .collect::<Result<Vec<_>>>()?;
+        let events = self.update(&master_state, &states, Instant::now());
+        if let Some(hook) = self.on_recovered.as_mut() {
+            for event in &events {
+                if let RecoveryEvent::SlaveRecovered { config, .. } = event {
+                    hook(master, *config)?;
+                }
+            }
+        }
+        Ok(events)
+    }
+
+    /// Record the states of the slave configs (in the order given to `new`).
+    fn update(
+        &mut self,
+        master_state: &MasterState,
+        states: &[SlaveConfigState],
+        now: Instant,
+    ) -> Vec<RecoveryEvent> {
+        let mut events = vec![];
+        let responding = master_state.slaves_responding;
+        if let Some(from) = self.slaves_responding.filter(|r| *r != responding) {
+            events.push(RecoveryEvent::RespondingChanged {
+                from,
+                to: responding,
+            });
+        }
+        self.slaves_responding = Some(responding);
+
+        for ((config, status), state) in self.configs.iter_mut().zip(states) {
+            let config = *config;
+            let ok = state.online && state.operational;
+            match *status {
+                SlaveStatus::Starting if ok => *status = SlaveStatus::Operational,
+                SlaveStatus::Starting => {}
+                SlaveStatus::Operational if ok => {}
+                SlaveStatus::Operational => {
+                    events.push(match state.al_state {
+                        Some(al_state) if state.online => {
+                            RecoveryEvent::SlaveNotOperational { config, al_state }
+                        }
+                        _ => RecoveryEvent::SlaveOffline { config },
+                    });
+                    *status = SlaveStatus::Lost {
+                        since: now,
+                        al_state: state.al_state,
+                    };
+                }
+                SlaveStatus::Lost { since, .. } if ok => {
+                    events.push(RecoveryEvent::SlaveRecovered {
+                        config,
+                        downtime: now.saturating_duration_since(since),
+                    });
+                    *status = SlaveStatus::Operational;
+                }
+                SlaveStatus::Lost {
+                    since,
+                    al_state: last,
+                } => {
+                    if state.al_state != last {
+                        if state.online {
+                            events.push(RecoveryEvent::Reconfiguring {
+                                config,
+                                al_state: state.al_state,
+                            });
+                        } else {
+                            events.push(RecoveryEvent::SlaveOffline { config });
+                        }
+                        *status = SlaveStatus::Lost {
+                            since,
+                            al_state: state.al_state,
+                        };
+                    }
+                }
+            }
+        }
+        events
+    }
+}
+
+#[test]
+fn test_recovery_supervisor() {
+    let master = |slaves_responding| MasterState {
+        slaves_responding,
+        al_states: 8,
+        link_up: true,
+    };
+    let state = |al_state: Option<AlState>| SlaveConfigState {
+        online: al_state.is_some(),
+        operational: al_state == Some(AlState::Op),
+        al_state,
+    };
+    let op = state(Some(AlState::Op));
+    let t0 = Instant::now();
+    let at = |ms| t0 + Duration::from_millis(ms);
+    let mut sup = RecoverySupervisor::new(vec![0, 1]);
+
+    // slaves that are not operational yet are not reported
+    let pre_op = state(Some(AlState::PreOp));
+    assert!(sup.update(&master(2), &[pre_op, pre_op], at(0)).is_empty());
+    assert!(!sup.all_operational());
+    assert!(sup.update(&master(2), &[op, op], at(10)).is_empty());
+    assert!(sup.all_operational());
+
+    assert_eq!(
+        sup.update(&master(1), &[op, state(None)], at(20)),
+        vec![
+            RecoveryEvent::RespondingChanged { from: 2, to: 1 },
+            RecoveryEvent::SlaveOffline { config: 1 },
+        ]
+    );
+    assert_eq!(sup.lost().collect::<Vec<_>>(), vec![1]);
+    assert!(sup
+        .update(&master(1), &[op, state(None)], at(30))
+        .is_empty());
+    assert_eq!(
+        sup.update(&master(2), &[op, state(Some(AlState::Init))], at(40)),
+        vec![
+            RecoveryEvent::RespondingChanged { from: 1, to: 2 },
+            RecoveryEvent::Reconfiguring {
+                config: 1,
+                al_state: Some(AlState::Init)
+            },
+        ]
+    );
+    assert_eq!(
+        sup.update(&master(2), &[op, op], at(120)),
+        vec![RecoveryEvent::SlaveRecovered {
+            config: 1,
+            downtime: Duration::from_millis(100)
+        }]
+    );
+    assert!(sup.all_operational());
+
+    assert_eq!(
+        sup.update(&master(2), &[state(Some(AlState::SafeOp)), op], at(130)),
+        vec![RecoveryEvent::SlaveNotOperational {
+            config: 0,
+            al_state: AlState::SafeOp
+        }]
+    );
+}
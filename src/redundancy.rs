@@ -0,0 +1,148 @@
+// Part of ethercat-rs. Copyright 2018-2020 by the authors.
+// This work is dual-licensed under Apache 2.0 and MIT terms.
+
+use crate::{master::Master, types::*};
+
+/// Link states of the master devices and the use of the redundant path.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RedundancyState {
+    /// State per device, main device first.
+    pub devices: Vec<MasterState>,
+    /// Frames of at least one domain came back over the backup device,
+    /// i.e. the ring is broken somewhere.
+    pub redundancy_active: bool,
+}
+
+impl RedundancyState {
+    /// A backup device is configured.
+    pub fn has_backup(&self) -> bool {
+        self.devices.len() > 1
+    }
+
+    /// The ring is broken, but the bus is still operating on the redundant path.
+    pub fn is_degraded(&self) -> bool {
+        self.has_backup() && (self.redundancy_active || self.devices.iter().any(|d| !d.link_up))
+    }
+}
+
+/// A change of the [`RedundancyState`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RedundancyEvent {
+    LinkDown {
+        device: usize,
+    },
+    LinkUp {
+        device: usize,
+    },
+    /// The redundant path is now in use.
+    RedundancyActivated,
+    /// All frames take the main path again.
+    RedundancyDeactivated,
+}
+
+/// Reports changes of the device links and the redundancy state.
+///
+/// The redundancy state is part of the domain state, which is only
+/// available to the application that requested the master. Therefore,
+/// [`RedundancyMonitor::poll`] is meant to be called from the cyclic task
+/// after processing the domains (not necessarily in every cycle).
+#[derive(Debug, Clone)]
+pub struct RedundancyMonitor {
+    device_count: usize,
+    domains: Vec<DomainIdx>,
+    state: Option<RedundancyState>,
+}
+
+impl RedundancyMonitor {
+    /// Create a monitor for the given domains.
+    ///
+    /// The number of devices is read once here, so that polling does not
+    /// need to lock the master.
+    pub fn new(master: &Master, domains: Vec<DomainIdx>) -> Result<Self> {
+        Ok(Self {
+            device_count: master.device_count()?,
+            domains,
+            state: None,
+        })
+    }
+
+    pub const fn device_count(&self) -> usize {
+        self.device_count
+    }
+
+    /// The state at the last poll.
+    pub const fn state(&self) -> Option<&RedundancyState> {
+        self.state.as_ref()
+    }
+
+    /// Read the current state and return the changes since the last poll.
+    pub fn poll(&mut self, master: &Master) -> Result<Vec<RedundancyEvent>> {
+        let state = master.redundancy_state_of(self.device_count, &self.domains)?;
+        Ok(self.update(state))
+    }
+
+    fn update(&mut self, new: RedundancyState) -> Vec<RedundancyEvent> {
+        let mut events = vec![];
+        let old = self.state.as_ref();
+        for (device, dev) in new.devices.iter().enumerate() {
+            let was_up = old
+                .and_then(|s| s.devices.get(device))
+                .map_or(true, |d| d.link_up);
+            if was_up && !dev.link_up {
+                events.push(RedundancyEvent::LinkDown { device });
+            } else if !was_up && dev.link_up {
+                events.push(RedundancyEvent::LinkUp { device });
+            }
+        }
+        let was_active = old.is_some_and(|s| s.redundancy_active);
+        if !was_active && new.redundancy_active {
+            events.push(RedundancyEvent::RedundancyActivated);
+        } else if was_active && !new.redundancy_active {
+            events.push(RedundancyEvent::RedundancyDeactivated);
+        }
+        self.state = Some(new);
+        events
+    }
+}
+
+#[test]
+fn test_redundancy_monitor() {
+    let state = |main, backup, active| RedundancyState {
+        devices: vec![
+            MasterState {
+                slaves_responding: 2,
+                al_states: 8,
+                link_up: main,
+            },
+            MasterState {
+                slaves_responding: 2,
+                al_states: 8,
+                link_up: backup,
+            },
+        ],
+        redundancy_active: active,
+    };
+    let mut mon = RedundancyMonitor {
+        device_count: 2,
+        domains: vec![],
+        state: None,
+    };
+    assert!(mon.update(state(true, true, false)).is_empty());
+    assert!(!mon.state().unwrap().is_degraded());
+    assert_eq!(
+        mon.update(state(true, true, true)),
+        vec![RedundancyEvent::RedundancyActivated]
+    );
+    assert!(mon.state().unwrap().is_degraded());
+    assert_eq!(
+        mon.update(state(false, true, true)),
+        vec![RedundancyEvent::LinkDown { device: 0 }]
+    );
+    assert_eq!(
+        mon.update(state(true, true, false)),
+        vec![
+            RedundancyEvent::LinkUp { device: 0 },
+            RedundancyEvent::RedundancyDeactivated
+        ]
+    );
+}
@@ -0,0 +1,96 @@
+// Part of ethercat-rs. Copyright 2018-2020 by the authors.
+// This work is dual-licensed under Apache 2.0 and MIT terms.
+
+use crate::types::{Error, Result};
+use std::{io, mem, ptr};
+
+const NSEC_PER_SEC: u64 = 1_000_000_000;
+
+/// Lock all current and future pages of the process into RAM.
+pub(crate) fn lock_memory() -> Result<()> {
+    let res = unsafe { libc::mlockall(libc::MCL_CURRENT | libc::MCL_FUTURE) };
+    if res < 0 {
+        return Err(Error::Io(io::Error::last_os_error()));
+    }
+    Ok(())
+}
+
+/// Switch the calling thread to `SCHED_FIFO` with the given priority.
+pub(crate) fn set_fifo_priority(priority: i32) -> Result<()> {
+    let param = libc::sched_param {
+        sched_priority: priority,
+    };
+    let res =
+        unsafe { libc::pthread_setschedparam(libc::pthread_self(), libc::SCHED_FIFO, &param) };
+    if res != 0 {
+        return Err(Error::Io(io::Error::from_raw_os_error(res)));
+    }
+    Ok(())
+}
+
+/// Pin the calling thread to the given CPUs.
+///
+/// Fails with `EINVAL` for CPU indices beyond `CPU_SETSIZE`.
+pub(crate) fn set_cpu_affinity(cpus: &[usize]) -> Result<()> {
+    if cpus.iter().any(|&cpu| cpu >= libc::CPU_SETSIZE as usize) {
+        return Err(Error::Io(io::Error::from_raw_os_error(libc::EINVAL)));
+    }
+    let mut set: libc::cpu_set_t = unsafe { mem::zeroed() };
+    unsafe { libc::CPU_ZERO(&mut set) };
+    for &cpu in cpus {
+        unsafe { libc::CPU_SET(cpu, &mut set) };
+    }
+    let res = unsafe { libc::sched_setaffinity(0, mem::size_of::<libc::cpu_set_t>(), &set) };
+    if res < 0 {
+        return Err(Error::Io(io::Error::last_os_error()));
+    }
+    Ok(())
+}
+
+/// Current value of `CLOCK_MONOTONIC` in nanoseconds.
+pub(crate) fn monotonic_ns() -> u64 {
+    let mut ts = libc::timespec {
+        tv_sec: 0,
+        tv_nsec: 0,
+    };
+    unsafe { libc::clock_gettime(libc::CLOCK_MONOTONIC, &mut ts) };
+    ts.tv_sec as u64 * NSEC_PER_SEC + ts.tv_nsec as u64
+}
+
+/// Sleep until `CLOCK_MONOTONIC` reaches the absolute time `deadline_ns`.
+pub(crate) fn sleep_until_ns(deadline_ns: u64) -> Result<()> {
+    let ts = libc::timespec {
+        tv_sec: (deadline_ns / NSEC_PER_SEC) as libc::time_t,
+        tv_nsec: (deadline_ns % NSEC_PER_SEC) as libc::c_long,
+    };
+    loop {
+        let res = unsafe {
+            libc::clock_nanosleep(
+                libc::CLOCK_MONOTONIC,
+                libc::TIMER_ABSTIME,
+                &ts,
+                ptr::null_mut(),
+            )
+        };
+        match res {
+            0 => return Ok(()),
+            libc::EINTR => continue,
+            e => return Err(Error::Io(io::Error::from_raw_os_error(e))),
+        }
+    }
+}
+
+#[test]
+fn test_sleep_until_ns() {
+    let start = monotonic_ns();
+    sleep_until_ns(start + 1_000_000).unwrap();
+    assert!(monotonic_ns() >= start + 1_000_000);
+    // deadlines in the past return immediately
+    sleep_until_ns(start).unwrap();
+}
+
+#[test]
+fn test_set_cpu_affinity_out_of_range() {
+    let err = set_cpu_affinity(&[libc::CPU_SETSIZE as usize]).unwrap_err();
+    assert!(matches!(err, Error::Io(e) if e.raw_os_error() == Some(libc::EINVAL)));
+}
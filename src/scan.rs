@@ -0,0 +1,53 @@
+// Part of ethercat-rs. Copyright 2018-2020 by the authors.
+// This work is dual-licensed under Apache 2.0 and MIT terms.
+
+use crate::{topology::Topology, types::*};
+
+/// The state of the whole bus at one point in time, see [`Master::scan`](crate::Master::scan).
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BusSnapshot {
+    pub master: MasterInfo,
+    /// All slaves, indexed by ring position.
+    pub slaves: Vec<SlaveSnapshot>,
+    /// All slave configurations of the application.
+    pub configs: Vec<ConfigInfo>,
+}
+
+impl BusSnapshot {
+    pub fn topology(&self) -> Topology {
+        let slaves: Vec<_> = self.slaves.iter().map(|s| s.info.clone()).collect();
+        Topology::from_slaves(&slaves)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SlaveSnapshot {
+    pub info: SlaveInfo,
+    pub syncs: Vec<SyncSnapshot>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SyncSnapshot {
+    pub info: SmInfo,
+    pub pdos: Vec<PdoSnapshot>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PdoSnapshot {
+    pub index: u16,
+    pub name: String,
+    pub entries: Vec<PdoEntrySnapshot>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PdoEntrySnapshot {
+    pub index: u16,
+    pub subindex: u8,
+    pub bit_len: u8,
+    pub name: String,
+}
@@ -0,0 +1,170 @@
+// Part of ethercat-rs. Copyright 2018-2020 by the authors.
+// This work is dual-licensed under Apache 2.0 and MIT terms.
+
+use crate::{master::Master, types::*};
+
+/// A domain exchanged every `divisor`-th cycle.
+#[derive(Debug, Clone)]
+struct ScheduledDomain {
+    idx: DomainIdx,
+    divisor: u64,
+    offset: u64,
+    /// Queued in the previous cycle, so the frames are received in this one.
+    queued: bool,
+    /// Processed in the current cycle.
+    processed: bool,
+    state: Option<DomainState>,
+}
+
+impl ScheduledDomain {
+    fn is_due(&self, cycle: u64) -> bool {
+        cycle % self.divisor == self.offset
+    }
+}
+
+/// Processes and queues domains with different update rates.
+///
+/// Every domain gets a cycle divisor: a servo domain with divisor 1 is
+/// exchanged in every cycle, a slow IO domain with divisor 10 only in every
+/// 10th, so it does not take up bandwidth in the other cycles. Offsets
+/// spread several slow domains over different cycles.
+///
+/// A domain queued in cycle `n` is processed at the start of cycle `n + 1`;
+/// outputs written in cycle `n` are sent if [`DomainScheduler::is_due`] for
+/// `n`, inputs are fresh if [`DomainScheduler::is_processed`].
+///
+/// The scheduler is driven by the [`CyclicRunner`](crate::CyclicRunner)
+/// (see [`CyclicConfig::schedule`](crate::CyclicConfig::schedule)), but can
+/// also be used in a custom cyclic task.
+#[derive(Debug, Clone, Default)]
+pub struct DomainScheduler {
+    domains: Vec<ScheduledDomain>,
+}
+
+impl DomainScheduler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Exchange a domain every `divisor`-th cycle, in the cycles where
+    /// `cycle % divisor == offset % divisor`.
+    ///
+    /// A divisor of 0 is treated as 1.
+    pub fn with_domain(mut self, idx: DomainIdx, divisor: u32, offset: u32) -> Self {
+        self.add(idx, divisor, offset);
+        self
+    }
+
+    /// See [`DomainScheduler::with_domain`].
+    pub fn add(&mut self, idx: DomainIdx, divisor: u32, offset: u32) {
+        let divisor = u64::from(divisor.max(1));
+        self.domains.push(ScheduledDomain {
+            idx,
+            divisor,
+            offset: u64::from(offset) % divisor,
+            queued: false,
+            processed: false,
+            state: None,
+        });
+    }
+
+    pub fn domains(&self) -> impl Iterator<Item = DomainIdx> + '_ {
+        self.domains.iter().map(|d| d.idx)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.domains.is_empty()
+    }
+
+    /// The domain is queued (and its outputs are sent) in `cycle`.
+    pub fn is_due(&self, idx: DomainIdx, cycle: u64) -> bool {
+        self.get(idx).is_some_and(|d| d.is_due(cycle))
+    }
+
+    /// The domain was processed in the current cycle, i.e. its inputs are fresh.
+    pub fn is_processed(&self, idx: DomainIdx) -> bool {
+        self.get(idx).is_some_and(|d| d.processed)
+    }
+
+    /// The state of the domain after its last exchange (`None` before the first).
+    pub fn state(&self, idx: DomainIdx) -> Option<&DomainState> {
+        self.get(idx).and_then(|d| d.state.as_ref())
+    }
+
+    /// The working counter states of all domains after their last exchange.
+    pub fn wc_states(&self) -> impl Iterator<Item = (DomainIdx, Option<WcState>)> + '_ {
+        self.domains
+            .iter()
+            .map(|d| (d.idx, d.state.as_ref().map(|s| s.wc_state)))
+    }
+
+    /// Process the domains that were queued in the previous cycle and read
+    /// their state.
+    ///
+    /// Call this after [`Master::receive`].
+    pub fn process(&mut self, master: &mut Master) -> Result<()> {
+        for d in &mut self.domains {
+            d.processed = false;
+            if d.queued {
+                let mut domain = master.domain(d.idx);
+                domain.process()?;
+                d.state = Some(domain.state()?);
+                d.queued = false;
+                d.processed = true;
+            }
+        }
+        Ok(())
+    }
+
+    /// Queue the domains that are due in `cycle`.
+    ///
+    /// Call this before [`Master::send`].
+    pub fn queue(&mut self, master: &mut Master, cycle: u64) -> Result<()> {
+        for d in &mut self.domains {
+            if d.is_due(cycle) {
+                master.domain(d.idx).queue()?;
+                d.queued = true;
+            }
+        }
+        Ok(())
+    }
+
+    fn get(&self, idx: DomainIdx) -> Option<&ScheduledDomain> {
+        self.domains.iter().find(|d| d.idx == idx)
+    }
+}
+
+#[test]
+fn test_domain_scheduler() {
+    let fast = DomainIdx::from(0);
+    let slow = DomainIdx::from(1);
+    let other = DomainIdx::from(2);
+    let sched = DomainScheduler::new()
+        .with_domain(fast, 1, 0)
+        .with_domain(slow, 10, 0)
+        .with_domain(other, 10, 15)
+        .with_domain(DomainIdx::from(3), 0, 0);
+
+    let due = |idx| {
+        (0..20)
+            .filter(|c| sched.is_due(idx, *c))
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(due(fast), (0..20).collect::<Vec<_>>());
+    assert_eq!(due(slow), vec![0, 10]);
+    assert_eq!(due(other), vec![5, 15]);
+    assert_eq!(due(DomainIdx::from(3)).len(), 20);
+    assert!(!sched.is_due(DomainIdx::from(4), 0));
+
+    assert!(!sched.is_processed(fast));
+    assert_eq!(sched.state(slow), None);
+    assert_eq!(
+        sched.wc_states().collect::<Vec<_>>(),
+        vec![
+            (fast, None),
+            (slow, None),
+            (other, None),
+            (DomainIdx::from(3), None)
+        ]
+    );
+}
@@ -0,0 +1,62 @@
+// Part of ethercat-rs. Copyright 2018-2020 by the authors.
+// This work is dual-licensed under Apache 2.0 and MIT terms.
+
+//! Serde support for the types of `ethercat-types` used in the info types.
+
+use crate::types::{AlState, SlavePos, SmIdx};
+use serde::{de::Error as _, Deserialize, Deserializer, Serialize, Serializer};
+use std::convert::TryFrom;
+
+pub(crate) mod al_state {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(state: &AlState, s: S) -> Result<S::Ok, S::Error> {
+        u8::from(*state).serialize(s)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<AlState, D::Error> {
+        let raw = u8::deserialize(d)?;
+        AlState::try_from(raw).map_err(|_| D::Error::custom(format!("invalid AL state {}", raw)))
+    }
+}
+
+pub(crate) mod al_state_opt {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(state: &Option<AlState>, s: S) -> Result<S::Ok, S::Error> {
+        state.map(u8::from).serialize(s)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Option<AlState>, D::Error> {
+        Option::<u8>::deserialize(d)?
+            .map(|raw| {
+                AlState::try_from(raw)
+                    .map_err(|_| D::Error::custom(format!("invalid AL state {}", raw)))
+            })
+            .transpose()
+    }
+}
+
+pub(crate) mod slave_pos_opt {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(pos: &Option<SlavePos>, s: S) -> Result<S::Ok, S::Error> {
+        pos.map(u16::from).serialize(s)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Option<SlavePos>, D::Error> {
+        Ok(Option::<u16>::deserialize(d)?.map(SlavePos::from))
+    }
+}
+
+pub(crate) mod sm_idx {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(idx: &SmIdx, s: S) -> Result<S::Ok, S::Error> {
+        u8::from(*idx).serialize(s)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<SmIdx, D::Error> {
+        Ok(SmIdx::from(u8::deserialize(d)?))
+    }
+}
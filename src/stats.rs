@@ -0,0 +1,244 @@
+// Part of ethercat-rs. Copyright 2018-2020 by the authors.
+// This work is dual-licensed under Apache 2.0 and MIT terms.
+
+use std::{fmt, time::Duration};
+
+/// Number of histogram buckets of [`TimingStats`].
+///
+/// Bucket 0 counts samples below 1 µs, bucket `i` counts samples in
+/// `[2^(i-1) µs, 2^i µs)` and the last bucket counts everything above.
+pub const HISTOGRAM_BUCKETS: usize = 16;
+
+/// Min/max/mean and a histogram of a series of durations.
+///
+/// Recording a sample never allocates.
+#[derive(Debug, Clone, Copy)]
+pub struct TimingStats {
+    count: u64,
+    min_ns: u64,
+    max_ns: u64,
+    sum_ns: u64,
+    histogram: [u64; HISTOGRAM_BUCKETS],
+}
+
+impl Default for TimingStats {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TimingStats {
+    pub const fn new() -> Self {
+        Self {
+            count: 0,
+            min_ns: u64::MAX,
+            max_ns: 0,
+            sum_ns: 0,
+            histogram: [0; HISTOGRAM_BUCKETS],
+        }
+    }
+
+    pub fn record(&mut self, sample: Duration) {
+        self.record_ns(sample.as_nanos() as u64);
+    }
+
+    pub(crate) fn record_ns(&mut self, ns: u64) {
+        self.count += 1;
+        self.min_ns = self.min_ns.min(ns);
+        self.max_ns = self.max_ns.max(ns);
+        self.sum_ns = self.sum_ns.saturating_add(ns);
+        self.histogram[bucket(ns)] += 1;
+    }
+
+    pub const fn count(&self) -> u64 {
+        self.count
+    }
+
+    pub fn min(&self) -> Duration {
+        if self.count == 0 {
+            Duration::from_nanos(0)
+        } else {
+            Duration::from_nanos(self.min_ns)
+        }
+    }
+
+    pub fn max(&self) -> Duration {
+        Duration::from_nanos(self.max_ns)
+    }
+
+    pub fn mean(&self) -> Duration {
+        Duration::from_nanos(self.sum_ns.checked_div(self.count).unwrap_or(0))
+    }
+
+    pub const fn histogram(&self) -> &[u64; HISTOGRAM_BUCKETS] {
+        &self.histogram
+    }
+
+    /// Exclusive upper bound of a histogram bucket (`None` for the last one).
+    pub fn bucket_limit(idx: usize) -> Option<Duration> {
+        if idx + 1 >= HISTOGRAM_BUCKETS {
+            None
+        } else {
+            Some(Duration::from_micros(1 << idx))
+        }
+    }
+}
+
+fn bucket(ns: u64) -> usize {
+    let us = ns / 1_000;
+    let idx = (u64::BITS - us.leading_zeros()) as usize;
+    idx.min(HISTOGRAM_BUCKETS - 1)
+}
+
+impl fmt::Display for TimingStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "min {:?} / mean {:?} / max {:?}",
+            self.min(),
+            self.mean(),
+            self.max()
+        )
+    }
+}
+
+/// Timing statistics of a cyclic task.
+#[derive(Debug, Clone, Copy)]
+pub struct CycleStats {
+    period_ns: u64,
+    last_latency_ns: Option<u64>,
+    /// Number of recorded cycles.
+    pub cycles: u64,
+    /// Number of cycles that did not finish before the next wake-up.
+    pub overruns: u64,
+    /// Delay between the scheduled and the actual wake-up.
+    pub wakeup_latency: TimingStats,
+    /// Deviation of the actual period from the nominal one.
+    pub period_jitter: TimingStats,
+    /// Time spent in receiving the frames and processing the domains.
+    pub receive_time: TimingStats,
+    /// Time spent in the user callback.
+    pub exec_time: TimingStats,
+    /// Time spent in queueing the domains and sending the frames.
+    pub send_time: TimingStats,
+}
+
+impl CycleStats {
+    pub const fn new(period: Duration) -> Self {
+        Self {
+            period_ns: period.as_nanos() as u64,
+            last_latency_ns: None,
+            cycles: 0,
+            overruns: 0,
+            wakeup_latency: TimingStats::new(),
+            period_jitter: TimingStats::new(),
+            receive_time: TimingStats::new(),
+            exec_time: TimingStats::new(),
+            send_time: TimingStats::new(),
+        }
+    }
+
+    pub fn period(&self) -> Duration {
+        Duration::from_nanos(self.period_ns)
+    }
+
+    /// Record the timing of one cycle.
+    ///
+    /// The period jitter is derived from the wake-up latencies of two
+    /// consecutive cycles. Returns `true` if the cycle overran its period.
+    pub fn record(
+        &mut self,
+        wakeup_latency: Duration,
+        receive_time: Duration,
+        exec_time: Duration,
+        send_time: Duration,
+    ) -> bool {
+        self.record_ns(
+            wakeup_latency.as_nanos() as u64,
+            receive_time.as_nanos() as u64,
+            exec_time.as_nanos() as u64,
+            send_time.as_nanos() as u64,
+        )
+    }
+
+    pub(crate) fn record_ns(
+        &mut self,
+        latency_ns: u64,
+        receive_ns: u64,
+        exec_ns: u64,
+        send_ns: u64,
+    ) -> bool {
+        self.cycles += 1;
+        self.wakeup_latency.record_ns(latency_ns);
+        if let Some(last) = self.last_latency_ns {
+            self.period_jitter.record_ns(latency_ns.abs_diff(last));
+        }
+        self.last_latency_ns = Some(latency_ns);
+        self.receive_time.record_ns(receive_ns);
+        self.exec_time.record_ns(exec_ns);
+        self.send_time.record_ns(send_ns);
+
+        let overrun = latency_ns + receive_ns + exec_ns + send_ns > self.period_ns;
+        if overrun {
+            self.overruns += 1;
+        }
+        overrun
+    }
+
+    /// Clear all recorded samples.
+    pub fn reset(&mut self) {
+        *self = Self::new(self.period());
+    }
+}
+
+impl fmt::Display for CycleStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} cycles, {} overruns; latency {}; jitter {}; receive {}; exec {}; send {}",
+            self.cycles,
+            self.overruns,
+            self.wakeup_latency,
+            self.period_jitter,
+            self.receive_time,
+            self.exec_time,
+            self.send_time
+        )
+    }
+}
+
+#[test]
+fn test_timing_stats() {
+    let mut stats = TimingStats::new();
+    assert_eq!(stats.mean(), Duration::from_nanos(0));
+    assert_eq!(stats.min(), Duration::from_nanos(0));
+    stats.record(Duration::from_nanos(500));
+    stats.record(Duration::from_micros(3));
+    stats.record(Duration::from_secs(1));
+    assert_eq!(stats.count(), 3);
+    assert_eq!(stats.min(), Duration::from_nanos(500));
+    assert_eq!(stats.max(), Duration::from_secs(1));
+    assert_eq!(stats.mean(), Duration::from_nanos(333_334_500));
+    assert_eq!(stats.histogram()[0], 1);
+    assert_eq!(stats.histogram()[2], 1);
+    assert_eq!(stats.histogram()[HISTOGRAM_BUCKETS - 1], 1);
+    assert_eq!(TimingStats::bucket_limit(2), Some(Duration::from_micros(4)));
+    assert_eq!(TimingStats::bucket_limit(HISTOGRAM_BUCKETS - 1), None);
+}
+
+#[test]
+fn test_cycle_stats() {
+    let us = Duration::from_micros;
+    let mut stats = CycleStats::new(us(1_000));
+    assert!(!stats.record(us(10), us(20), us(100), us(20)));
+    assert!(!stats.record(us(40), us(20), us(100), us(20)));
+    assert!(stats.record(us(5), us(20), us(990), us(20)));
+    assert_eq!(stats.cycles, 3);
+    assert_eq!(stats.overruns, 1);
+    assert_eq!(stats.period_jitter.count(), 2);
+    assert_eq!(stats.period_jitter.min(), us(30));
+    assert_eq!(stats.period_jitter.max(), us(35));
+    stats.reset();
+    assert_eq!(stats.cycles, 0);
+    assert_eq!(stats.period(), us(1_000));
+}
@@ -0,0 +1,275 @@
+// Part of ethercat-rs. Copyright 2018-2020 by the authors.
+// This work is dual-licensed under Apache 2.0 and MIT terms.
+
+use crate::types::*;
+use std::fmt::{self, Write};
+
+/// Value of `next_slave` if there is no slave behind a port.
+const NO_SLAVE: u16 = 0xFFFF;
+
+/// Order in which the ports of a slave forward frames (port 0 is upstream).
+const PORT_ORDER: [usize; 3] = [3, 1, 2];
+
+/// Connection from a port of a slave to the next slave downstream.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TopologyLink {
+    pub port: u8,
+    pub slave: u16,
+    /// Propagation delay to the next DC slave behind this port in nanoseconds.
+    pub delay: u32,
+}
+
+/// State of a port that does not lead to another slave.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OpenPort {
+    /// The port is implemented, but nothing is connected (loop closed).
+    Unconnected(u8),
+    /// The port has a link, but no slave is behind it (e.g. the cable back
+    /// to the backup device of a redundant master).
+    LinkWithoutSlave(u8),
+}
+
+/// A slave in the [`Topology`].
+#[derive(Debug, Clone)]
+pub struct TopologyNode {
+    pub position: u16,
+    pub name: String,
+    /// Upstream slave and its port this slave is connected to.
+    pub parent: Option<(u16, u8)>,
+    /// Downstream slaves, in the order they are passed by the frames.
+    pub children: Vec<TopologyLink>,
+    pub open_ports: Vec<OpenPort>,
+    /// Propagation delay from the reference clock in nanoseconds.
+    pub transmission_delay: u32,
+}
+
+impl TopologyNode {
+    /// The slave connects more than one downstream segment (e.g. an EK1122).
+    pub fn is_junction(&self) -> bool {
+        self.children.len() > 1
+    }
+}
+
+/// The physical topology of the bus, as determined by the master.
+#[derive(Debug, Clone, Default)]
+pub struct Topology {
+    /// All slaves, indexed by ring position.
+    pub nodes: Vec<TopologyNode>,
+}
+
+impl Topology {
+    pub fn from_slaves(slaves: &[SlaveInfo]) -> Self {
+        Self::from_ports(slaves.iter().map(|s| {
+            (
+                s.ring_pos,
+                s.name.clone(),
+                &s.ports[..],
+                s.dc.transmission_delay,
+            )
+        }))
+    }
+
+    fn from_ports<'a>(
+        slaves: impl Iterator<Item = (u16, String, &'a [SlavePortInfo], u32)>,
+    ) -> Self {
+        let mut nodes: Vec<TopologyNode> = vec![];
+        for (position, name, ports, transmission_delay) in slaves {
+            let mut node = TopologyNode {
+                position,
+                name,
+                parent: None,
+                children: vec![],
+                open_ports: vec![],
+                transmission_delay,
+            };
+            for &i in &PORT_ORDER {
+                let port = match ports.get(i) {
+                    Some(port) => port,
+                    None => continue,
+                };
+                if let SlavePortType::NotImplemented = port.desc {
+                    continue;
+                }
+                if port.next_slave != NO_SLAVE {
+                    node.children.push(TopologyLink {
+                        port: i as u8,
+                        slave: port.next_slave,
+                        delay: port.delay_to_next_dc,
+                    });
+                } else if port.link.link_up {
+                    node.open_ports.push(OpenPort::LinkWithoutSlave(i as u8));
+                } else {
+                    node.open_ports.push(OpenPort::Unconnected(i as u8));
+                }
+            }
+            nodes.push(node);
+        }
+        let links: Vec<_> = nodes
+            .iter()
+            .flat_map(|n| n.children.iter().map(move |l| (n.position, *l)))
+            .collect();
+        for (parent, link) in links {
+            if let Some(child) = nodes.get_mut(usize::from(link.slave)) {
+                child.parent = Some((parent, link.port));
+            }
+        }
+        Self { nodes }
+    }
+
+    pub fn node(&self, position: u16) -> Option<&TopologyNode> {
+        self.nodes.get(usize::from(position))
+    }
+
+    /// Slaves without an upstream slave (normally only the first one).
+    pub fn roots(&self) -> impl Iterator<Item = &TopologyNode> {
+        self.nodes.iter().filter(|n| n.parent.is_none())
+    }
+
+    /// Render the topology in the Graphviz DOT language.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph bus {\n    rankdir=LR;\n    node [shape=box];\n");
+        for node in &self.nodes {
+            let _ = write!(
+                dot,
+                "    s{} [label=\"{}: {}",
+                node.position,
+                node.position,
+                node.name.replace('"', "\\\"")
+            );
+            if !node.open_ports.is_empty() {
+                let _ = write!(dot, "\\nopen ports: {}", open_ports(&node.open_ports));
+            }
+            dot.push_str("\"];\n");
+        }
+        for node in &self.nodes {
+            for link in &node.children {
+                let _ = writeln!(
+                    dot,
+                    "    s{} -> s{} [taillabel=\"{}\", label=\"{} ns\"];",
+                    node.position, link.slave, link.port, link.delay
+                );
+            }
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    fn fmt_node(
+        &self,
+        f: &mut fmt::Formatter,
+        node: &TopologyNode,
+        prefix: &str,
+        depth: usize,
+    ) -> fmt::Result {
+        write!(f, "{}: {}", node.position, node.name)?;
+        if !node.open_ports.is_empty() {
+            write!(f, " (open ports: {})", open_ports(&node.open_ports))?;
+        }
+        writeln!(f)?;
+        // guard against cycles in inconsistent port information
+        if depth > self.nodes.len() {
+            return Ok(());
+        }
+        for (i, link) in node.children.iter().enumerate() {
+            let last = i + 1 == node.children.len();
+            write!(
+                f,
+                "{}{} [{}] +{} ns ",
+                prefix,
+                if last { "└─" } else { "├─" },
+                link.port,
+                link.delay
+            )?;
+            let prefix = format!("{}{}", prefix, if last { "   " } else { "│  " });
+            match self.node(link.slave) {
+                Some(child) => self.fmt_node(f, child, &prefix, depth + 1)?,
+                None => writeln!(f, "{}: ?", link.slave)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+fn open_ports(ports: &[OpenPort]) -> String {
+    ports
+        .iter()
+        .map(|p| match p {
+            OpenPort::Unconnected(i) => i.to_string(),
+            OpenPort::LinkWithoutSlave(i) => format!("{} (link)", i),
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+impl fmt::Display for Topology {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for root in self.roots() {
+            self.fmt_node(f, root, "", 0)?;
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn test_topology() {
+    let port = |desc, next_slave, link_up, delay| SlavePortInfo {
+        desc,
+        link: SlavePortLink {
+            link_up,
+            loop_closed: !link_up,
+            signal_detected: link_up,
+        },
+        receive_time: 0,
+        next_slave,
+        delay_to_next_dc: delay,
+    };
+    let ebus = SlavePortType::EBus;
+    let none = SlavePortType::NotImplemented;
+    // junction 0 with a branch at port 3 (slave 1) and the line at port 1 (slave 2)
+    let slaves = [
+        [
+            port(ebus, NO_SLAVE, true, 0),
+            port(ebus, 2, true, 300),
+            port(ebus, NO_SLAVE, false, 0),
+            port(ebus, 1, true, 100),
+        ],
+        [
+            port(ebus, 0, true, 0),
+            port(ebus, NO_SLAVE, false, 0),
+            port(none, NO_SLAVE, false, 0),
+            port(none, NO_SLAVE, false, 0),
+        ],
+        [
+            port(ebus, 0, true, 0),
+            port(ebus, NO_SLAVE, true, 0),
+            port(none, NO_SLAVE, false, 0),
+            port(none, NO_SLAVE, false, 0),
+        ],
+    ];
+    let topo = Topology::from_ports(
+        slaves
+            .iter()
+            .enumerate()
+            .map(|(i, p)| (i as u16, format!("S{}", i), &p[..], 0)),
+    );
+    let root = topo.node(0).unwrap();
+    assert!(root.is_junction());
+    assert_eq!(root.children[0].slave, 1);
+    assert_eq!(root.children[1].delay, 300);
+    assert_eq!(root.open_ports, vec![OpenPort::Unconnected(2)]);
+    assert_eq!(topo.node(2).unwrap().parent, Some((0, 1)));
+    assert_eq!(
+        topo.node(2).unwrap().open_ports,
+        vec![OpenPort::LinkWithoutSlave(1)]
+    );
+    assert_eq!(topo.roots().count(), 1);
+    assert_eq!(
+        topo.to_string(),
+        "0: S0 (open ports: 2)\n\
+         ├─ [3] +100 ns 1: S1 (open ports: 1)\n\
+         └─ [1] +300 ns 2: S2 (open ports: 1 (link))\n"
+    );
+    assert!(topo
+        .to_dot()
+        .contains("s0 -> s2 [taillabel=\"1\", label=\"300 ns\"];"));
+}
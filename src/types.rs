@@ -1,7 +1,7 @@
 // Part of ethercat-rs. Copyright 2018-2020 by the authors.
 // This work is dual-licensed under Apache 2.0 and MIT terms.
 
-use crate::ec;
+use crate::{al_status::AlStatusCode, ec, verify::BusReport};
 use derive_new::new;
 use std::io;
 use thiserror::Error;
@@ -22,6 +22,37 @@ pub enum Error {
     NotActivated,
     #[error("Invalid AL state 0x{0:X}")]
     InvalidAlState(u8),
+    #[error("Invalid port type {0}")]
+    InvalidPortType(u32),
+    #[error("Cyclic task panicked")]
+    CyclicTaskPanicked,
+    #[error("Cycle overran {0} times in a row")]
+    CycleOverrun(u32),
+    #[error("Cycle period must not be zero")]
+    InvalidCyclePeriod,
+    #[error("Invalid DC configuration: {0}")]
+    InvalidDcConfig(&'static str),
+    #[error("Invalid ESI file: {0}")]
+    Esi(String),
+    #[error("Bus does not match the expected layout: {0}")]
+    BusMismatch(BusReport),
+    #[error("Slave {position} refused the transition from {from:?} to {to:?}: {code}")]
+    StateRefused {
+        position: u16,
+        from: AlState,
+        to: AlState,
+        code: AlStatusCode,
+    },
+    #[error("Slave {position} did not reach {to:?} in time, it is in {current:?}{}",
+        code.map(|c| format!(" ({})", c)).unwrap_or_default())]
+    StateTimeout {
+        position: u16,
+        to: AlState,
+        current: AlState,
+        code: Option<AlStatusCode>,
+    },
+    #[error("Mailbox worker has stopped")]
+    WorkerStopped,
     #[error(transparent)]
     Io(#[from] io::Error),
 }
@@ -46,14 +77,16 @@ pub(crate) struct DomainDataPlacement {
 pub type SlaveConfigIdx = u32;
 
 /// An EtherCAT slave identification, consisting of vendor ID and product code.
-#[derive(Debug, Clone, Copy, new)]
+#[derive(Debug, Clone, Copy, PartialEq, new)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct SlaveId {
     pub vendor_id: u32,
     pub product_code: u32,
 }
 
 /// An EtherCAT slave revision identification.
-#[derive(Debug, Clone, Copy, new)]
+#[derive(Debug, Clone, Copy, PartialEq, new)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct SlaveRev {
     pub revision_number: u32,
     pub serial_number: u32,
@@ -61,7 +94,7 @@ pub struct SlaveRev {
 
 /// An EtherCAT slave, which is specified either by absolute position in the
 /// ring or by offset from a given alias.
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum SlaveAddr {
     ByPos(u16),
     ByAlias(u16, u16),
@@ -76,34 +109,208 @@ impl SlaveAddr {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct MasterInfo {
     pub slave_count: u32,
+    pub config_count: u32,
+    pub domain_count: u32,
+    pub eoe_handler_count: u32,
+    pub phase: MasterPhase,
+    pub active: bool,
+    /// Link state of the main device.
     pub link_up: bool,
     pub scan_busy: bool,
+    /// Main device first, followed by the backup device(s), if any.
+    pub devices: Vec<MasterDeviceInfo>,
+    /// Statistics summed over all devices.
+    pub stats: FrameStats,
+    /// Frame loss rates in frames/s.
+    pub loss_rates: Rates,
     pub app_time: u64,
+    pub dc_ref_time: u64,
+    /// Slave that provides the DC reference clock.
+    #[cfg_attr(feature = "serde", serde(with = "crate::serialize::slave_pos_opt"))]
+    pub ref_clock: Option<SlavePos>,
 }
 
-#[derive(Debug, Clone)]
+/// Phase of the master.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum MasterPhase {
+    /// No Ethernet device is attached.
+    Orphaned,
+    /// The master is not requested by an application.
+    Idle,
+    /// The master is in use by an application.
+    Operation,
+}
+
+/// An Ethernet device of the master.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MasterDeviceInfo {
+    pub mac_address: [u8; 6],
+    pub attached: bool,
+    pub link_up: bool,
+    pub stats: FrameStats,
+}
+
+/// Frame counters and rates of the master or one of its devices.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FrameStats {
+    pub tx_frames: u64,
+    pub rx_frames: u64,
+    pub tx_bytes: u64,
+    pub rx_bytes: u64,
+    /// Frames that could not be sent (only known per device).
+    pub tx_errors: u64,
+    /// Frames in frames/s.
+    pub tx_frame_rates: Rates,
+    pub rx_frame_rates: Rates,
+    /// Bytes in bytes/s.
+    pub tx_byte_rates: Rates,
+    pub rx_byte_rates: Rates,
+}
+
+impl FrameStats {
+    /// Frames that were sent, but not received again.
+    pub fn lost_frames(&self) -> u64 {
+        self.tx_frames.saturating_sub(self.rx_frames)
+    }
+}
+
+/// A rate averaged over the last second, 10 seconds and minute.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Rates {
+    pub sec_1: f64,
+    pub sec_10: f64,
+    pub sec_60: f64,
+}
+
+impl Rates {
+    pub(crate) fn from_raw(raw: [i32; 3], scale: f64) -> Self {
+        Self {
+            sec_1: f64::from(raw[0]) / scale,
+            sec_10: f64::from(raw[1]) / scale,
+            sec_60: f64::from(raw[2]) / scale,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct MasterState {
     pub slaves_responding: u32,
     pub al_states: u8,
     pub link_up: bool,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ConfigInfo {
     pub alias: u16,
     pub position: u16,
     pub id: SlaveId,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serialize::slave_pos_opt"))]
     pub slave_position: Option<SlavePos>,
-    pub sdo_count: u32,
-    pub idn_count: u32,
-    // TODO: more attributes are returned:
-    // syncs[*], watchdog_*, dc_*
+    /// Sync managers with a configured direction or assigned PDOs, with
+    /// their PDO assignment and mapping.
+    ///
+    /// The direction is [`SyncDirection::Invalid`] if PDOs were assigned
+    /// without configuring the sync manager.
+    pub syncs: Vec<ConfigSmInfo>,
+    pub watchdog_divider: u16,
+    pub watchdog_intervals: u16,
+    pub sdos: Vec<ConfigSdoInfo>,
+    pub idns: Vec<ConfigIdnInfo>,
+    pub dc: ConfigDcInfo,
 }
 
-#[derive(Debug, Clone)]
+/// Sync manager of a slave configuration.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ConfigSmInfo {
+    #[cfg_attr(feature = "serde", serde(with = "crate::serialize::sm_idx"))]
+    pub idx: SmIdx,
+    pub direction: SyncDirection,
+    pub watchdog_mode: WatchdogMode,
+    pub pdos: Vec<ConfigPdoInfo>,
+}
+
+/// PDO assigned to a sync manager of a slave configuration.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ConfigPdoInfo {
+    pub index: u16,
+    pub name: String,
+    pub entries: Vec<ConfigPdoEntryInfo>,
+}
+
+/// Entry of a PDO mapping of a slave configuration.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ConfigPdoEntryInfo {
+    pub index: u16,
+    pub subindex: u8,
+    pub bit_len: u8,
+    pub name: String,
+}
+
+/// SDO that is downloaded when the slave is configured.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ConfigSdoInfo {
+    pub index: u16,
+    pub subindex: u8,
+    pub complete_access: bool,
+    pub data: Vec<u8>,
+}
+
+/// SoE IDN that is written when the slave is configured.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ConfigIdnInfo {
+    pub drive_no: u8,
+    pub idn: u16,
+    /// State in which the IDN is written.
+    #[cfg_attr(feature = "serde", serde(with = "crate::serialize::al_state"))]
+    pub state: AlState,
+    pub data: Vec<u8>,
+}
+
+/// Distributed clock settings of a slave configuration.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ConfigDcInfo {
+    /// AssignActivate word, zero if DC is not used.
+    pub assign_activate: u16,
+    /// SYNC0 and SYNC1 signal.
+    pub sync: [ConfigSyncSignal; 2],
+}
+
+impl ConfigDcInfo {
+    pub const fn is_enabled(&self) -> bool {
+        self.assign_activate != 0
+    }
+}
+
+/// Cycle and shift time of a configured SYNC signal in nanoseconds.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ConfigSyncSignal {
+    pub cycle_time: u32,
+    pub shift_time: i32,
+}
+
+/// Information about a slave found on the bus.
+///
+/// The size of the process data RAM of the slave can be read with
+/// [`Master::slave_ram_size`](crate::Master::slave_ram_size).
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct SlaveInfo {
     pub name: String,
     pub ring_pos: u16,
@@ -111,14 +318,114 @@ pub struct SlaveInfo {
     pub rev: SlaveRev,
     pub alias: u16,
     pub current_on_ebus: i16,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serialize::al_state"))]
     pub al_state: AlState,
     pub error_flag: u8,
     pub sync_count: u8,
     pub sdo_count: u16,
     pub ports: [SlavePortInfo; ec::EC_MAX_PORTS as usize],
+    pub group: String,
+    pub order: String,
+    pub mailbox: SlaveMailboxInfo,
+    /// CoE details from the SII general category.
+    pub coe: CoeDetails,
+    /// The SII contains a general category.
+    pub has_general_category: bool,
+    /// SAFEOP can be reached without valid outputs.
+    pub enable_safeop: bool,
+    /// The slave does not support LRW datagrams.
+    pub enable_not_lrw: bool,
+    /// The FMMUs support bit-wise mapping.
+    pub fmmu_bit: bool,
+    pub dc: SlaveDcInfo,
+    /// Size of the SII image in bytes.
+    pub sii_size: usize,
 }
 
-#[derive(Debug, Clone, Copy)]
+/// Offset and size of a mailbox sync manager.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MailboxArea {
+    pub offset: u16,
+    pub size: u16,
+}
+
+/// Mailbox configuration of a slave, as read from the SII.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SlaveMailboxInfo {
+    pub boot_rx: MailboxArea,
+    pub boot_tx: MailboxArea,
+    pub std_rx: MailboxArea,
+    pub std_tx: MailboxArea,
+    pub protocols: MailboxProtocols,
+}
+
+/// Mailbox protocols supported by a slave.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MailboxProtocols {
+    /// ADS over EtherCAT
+    pub aoe: bool,
+    /// Ethernet over EtherCAT
+    pub eoe: bool,
+    /// CANopen over EtherCAT
+    pub coe: bool,
+    /// File access over EtherCAT
+    pub foe: bool,
+    /// Servo profile over EtherCAT
+    pub soe: bool,
+    /// Vendor specific protocol over EtherCAT
+    pub voe: bool,
+}
+
+impl From<u16> for MailboxProtocols {
+    fn from(bits: u16) -> Self {
+        Self {
+            aoe: bits & 0x01 != 0,
+            eoe: bits & 0x02 != 0,
+            coe: bits & 0x04 != 0,
+            foe: bits & 0x08 != 0,
+            soe: bits & 0x10 != 0,
+            voe: bits & 0x20 != 0,
+        }
+    }
+}
+
+/// CANopen over EtherCAT details of a slave.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CoeDetails {
+    pub enable_sdo: bool,
+    pub enable_sdo_info: bool,
+    pub enable_pdo_assign: bool,
+    pub enable_pdo_configuration: bool,
+    pub enable_upload_at_startup: bool,
+    pub enable_sdo_complete_access: bool,
+}
+
+/// Width of the distributed clock registers of a slave.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum DcRange {
+    Bits32,
+    Bits64,
+}
+
+/// Distributed clock capabilities of a slave.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SlaveDcInfo {
+    pub supported: bool,
+    pub range: DcRange,
+    /// The slave can be synchronised (has the system time registers).
+    pub has_system_time: bool,
+    /// Propagation delay from the reference clock in nanoseconds.
+    pub transmission_delay: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum SlavePortType {
     NotImplemented,
     NotConfigured,
@@ -132,14 +439,16 @@ impl Default for SlavePortType {
     }
 }
 
-#[derive(Debug, Default, Clone, Copy)]
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct SlavePortLink {
     pub link_up: bool,
     pub loop_closed: bool,
     pub signal_detected: bool,
 }
 
-#[derive(Debug, Default, Clone, Copy)]
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct SlavePortInfo {
     pub desc: SlavePortType,
     pub link: SlavePortLink,
@@ -148,21 +457,26 @@ pub struct SlavePortInfo {
     pub delay_to_next_dc: u32,
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct SlaveConfigState {
     pub online: bool,
     pub operational: bool,
-    pub al_state: AlState,
+    /// AL state of the slave, `None` while it is offline.
+    #[cfg_attr(feature = "serde", serde(with = "crate::serialize::al_state_opt"))]
+    pub al_state: Option<AlState>,
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum SyncDirection {
     Invalid,
     Output,
     Input,
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum WatchdogMode {
     Default,
     Enable,
@@ -170,8 +484,10 @@ pub enum WatchdogMode {
 }
 
 /// Sync Manager Info
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct SmInfo {
+    #[cfg_attr(feature = "serde", serde(with = "crate::serialize::sm_idx"))]
     pub idx: SmIdx,
     pub start_addr: u16,
     pub default_size: u16,
@@ -250,14 +566,120 @@ impl SdoData for &'_ [u8] {
     }
 }
 
-#[derive(Debug, Clone)]
+/// Domain information from the master, see [`Master::get_domain_info`](crate::Master::get_domain_info).
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DomainInfo {
+    pub data_size: usize,
+    pub logical_base_address: u32,
+    /// Last working counter per device.
+    pub working_counters: Vec<u16>,
+    pub expected_working_counter: u16,
+    /// FMMU configurations of all slave configs, ordered by logical address.
+    pub fmmus: Vec<DomainFmmuInfo>,
+}
+
+/// FMMU configuration of a slave config that maps data into a domain.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DomainFmmuInfo {
+    pub slave_config_alias: u16,
+    pub slave_config_position: u16,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serialize::sm_idx"))]
+    pub sync_index: SmIdx,
+    pub direction: SyncDirection,
+    pub logical_address: u32,
+    /// Offset of the data in the domain's process data.
+    pub offset: usize,
+    pub data_size: usize,
+}
+
+/// Number of working counter increments a slave config contributes to
+/// the working counter of a domain.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct WcContribution {
+    pub alias: u16,
+    pub position: u16,
+    pub expected: u16,
+}
+
+/// Maximum payload of a process data datagram.
+const MAX_DATAGRAM_DATA_SIZE: usize = 1486;
+
+impl DomainInfo {
+    /// Split the working counter expected by the master into the
+    /// contributions of the slave configs.
+    ///
+    /// Like the master, the FMMUs are distributed over datagrams of at most
+    /// 1486 bytes without splitting the data of a slave config. A slave config
+    /// counts once per direction and datagram; outputs count twice if the
+    /// datagram also contains inputs (LRW).
+    pub fn wc_contributions(&self) -> Vec<WcContribution> {
+        let config = |f: &DomainFmmuInfo| (f.slave_config_alias, f.slave_config_position);
+        let mut datagrams: Vec<Vec<&DomainFmmuInfo>> = vec![vec![]];
+        let mut datagram_offset = 0;
+        let mut candidate = 0;
+        for (i, fmmu) in self.fmmus.iter().enumerate() {
+            let current = datagrams.last_mut().unwrap();
+            if i == 0 || config(fmmu) != config(&self.fmmus[i - 1]) {
+                candidate = current.len();
+            }
+            if fmmu.offset + fmmu.data_size - datagram_offset > MAX_DATAGRAM_DATA_SIZE
+                && candidate > 0
+            {
+                let next = current.split_off(candidate);
+                datagram_offset = next.first().map_or(fmmu.offset, |f| f.offset);
+                datagrams.push(next);
+                candidate = 0;
+            }
+            datagrams.last_mut().unwrap().push(fmmu);
+        }
+
+        let mut result: Vec<WcContribution> = vec![];
+        for datagram in datagrams {
+            let has = |dir| datagram.iter().any(|f| f.direction == dir);
+            let output_weight = if has(SyncDirection::Input) { 2 } else { 1 };
+            let mut counted = vec![];
+            for fmmu in datagram {
+                let key = (config(fmmu), fmmu.direction);
+                if counted.contains(&key) {
+                    continue;
+                }
+                counted.push(key);
+                let weight = match fmmu.direction {
+                    SyncDirection::Output => output_weight,
+                    SyncDirection::Input => 1,
+                    SyncDirection::Invalid => 0,
+                };
+                let (alias, position) = config(fmmu);
+                match result
+                    .iter_mut()
+                    .find(|c| (c.alias, c.position) == (alias, position))
+                {
+                    Some(c) => c.expected += weight,
+                    None => result.push(WcContribution {
+                        alias,
+                        position,
+                        expected: weight,
+                    }),
+                }
+            }
+        }
+        result
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct DomainState {
     pub working_counter: u32,
     pub wc_state: WcState,
     pub redundancy_active: bool,
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum WcState {
     Zero = 0,
     Incomplete,
@@ -291,3 +713,44 @@ impl From<u32> for WcState {
         }
     }
 }
+
+#[test]
+fn test_wc_contributions() {
+    let fmmu = |position, sm: u8, direction, offset, data_size| DomainFmmuInfo {
+        slave_config_alias: 0,
+        slave_config_position: position,
+        sync_index: SmIdx::from(sm),
+        direction,
+        logical_address: offset as u32,
+        offset,
+        data_size,
+    };
+    let out = SyncDirection::Output;
+    let inp = SyncDirection::Input;
+    let mut info = DomainInfo {
+        data_size: 10,
+        logical_base_address: 0,
+        working_counters: vec![0],
+        expected_working_counter: 4,
+        fmmus: vec![
+            fmmu(0, 2, out, 0, 2),
+            fmmu(0, 3, inp, 2, 4),
+            fmmu(1, 3, inp, 6, 4),
+        ],
+    };
+    let wc = |c: &[WcContribution]| {
+        c.iter()
+            .map(|c| (c.position, c.expected))
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(wc(&info.wc_contributions()), vec![(0, 3), (1, 1)]);
+
+    // slave 2 does not fit into the first datagram, which now has only inputs
+    info.fmmus = vec![
+        fmmu(0, 3, inp, 0, 1000),
+        fmmu(1, 3, inp, 1000, 400),
+        fmmu(2, 2, out, 1400, 100),
+        fmmu(2, 3, inp, 1500, 100),
+    ];
+    assert_eq!(wc(&info.wc_contributions()), vec![(0, 1), (1, 1), (2, 3)]);
+}
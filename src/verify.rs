@@ -0,0 +1,362 @@
+// Part of ethercat-rs. Copyright 2018-2020 by the authors.
+// This work is dual-licensed under Apache 2.0 and MIT terms.
+
+use crate::types::*;
+use std::fmt;
+
+/// A slave that is expected on the bus.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ExpectedSlave {
+    pub addr: SlaveAddr,
+    pub id: SlaveId,
+    /// Expected revision number, if it matters.
+    pub revision: Option<u32>,
+    /// Expected serial number, if it matters.
+    pub serial: Option<u32>,
+}
+
+impl ExpectedSlave {
+    pub const fn new(addr: SlaveAddr, id: SlaveId) -> Self {
+        Self {
+            addr,
+            id,
+            revision: None,
+            serial: None,
+        }
+    }
+
+    pub const fn with_revision(mut self, revision: u32) -> Self {
+        self.revision = Some(revision);
+        self
+    }
+
+    pub const fn with_serial(mut self, serial: u32) -> Self {
+        self.serial = Some(serial);
+        self
+    }
+}
+
+/// The declared layout of the bus, to be verified against the slaves found
+/// by the master (see [`Master::verify_bus`](crate::Master::verify_bus)).
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BusLayout {
+    pub slaves: Vec<ExpectedSlave>,
+    /// Do not report slaves that are not part of the layout.
+    pub allow_extra: bool,
+}
+
+/// A deviation of the bus from the [`BusLayout`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum BusIssue {
+    /// No slave exists at the expected address.
+    Missing { addr: SlaveAddr, expected: SlaveId },
+    /// A slave was found that is not part of the layout.
+    Extra { position: u16, found: SlaveId },
+    /// A different device was found at the expected address.
+    WrongDevice {
+        position: u16,
+        expected: SlaveId,
+        found: SlaveId,
+    },
+    /// The expected device was found at another position, whose expected
+    /// device in turn sits at this position (e.g. swapped cables).
+    Swapped {
+        position: u16,
+        other_position: u16,
+        expected: SlaveId,
+        found: SlaveId,
+    },
+    WrongRevision {
+        position: u16,
+        expected: u32,
+        found: u32,
+    },
+    WrongSerial {
+        position: u16,
+        expected: u32,
+        found: u32,
+    },
+}
+
+impl fmt::Display for BusIssue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let id = |id: &SlaveId| format!("{:#x}:{:#x}", id.vendor_id, id.product_code);
+        match self {
+            BusIssue::Missing { addr, expected } => {
+                write!(f, "slave {:?} ({}) is missing", addr, id(expected))
+            }
+            BusIssue::Extra { position, found } => {
+                write!(f, "unexpected slave {} at position {}", id(found), position)
+            }
+            BusIssue::WrongDevice {
+                position,
+                expected,
+                found,
+            } => write!(
+                f,
+                "expected {} at position {}, found {}",
+                id(expected),
+                position,
+                id(found)
+            ),
+            BusIssue::Swapped {
+                position,
+                other_position,
+                expected,
+                found,
+            } => write!(
+                f,
+                "expected {} at position {}, found {} (swapped with position {})",
+                id(expected),
+                position,
+                id(found),
+                other_position
+            ),
+            BusIssue::WrongRevision {
+                position,
+                expected,
+                found,
+            } => write!(
+                f,
+                "slave at position {} has revision {:#x}, expected {:#x}",
+                position, found, expected
+            ),
+            BusIssue::WrongSerial {
+                position,
+                expected,
+                found,
+            } => write!(
+                f,
+                "slave at position {} has serial number {}, expected {}",
+                position, found, expected
+            ),
+        }
+    }
+}
+
+/// Result of verifying the bus against a [`BusLayout`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BusReport {
+    pub issues: Vec<BusIssue>,
+}
+
+impl BusReport {
+    pub fn is_ok(&self) -> bool {
+        self.issues.is_empty()
+    }
+
+    /// Turn a report with issues into an error.
+    pub fn into_result(self) -> Result<()> {
+        if self.is_ok() {
+            Ok(())
+        } else {
+            Err(Error::BusMismatch(self))
+        }
+    }
+}
+
+impl fmt::Display for BusReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.issues.is_empty() {
+            return write!(f, "bus matches the layout");
+        }
+        for (i, issue) in self.issues.iter().enumerate() {
+            if i > 0 {
+                write!(f, "; ")?;
+            }
+            write!(f, "{}", issue)?;
+        }
+        Ok(())
+    }
+}
+
+impl BusLayout {
+    pub fn new(slaves: Vec<ExpectedSlave>) -> Self {
+        Self {
+            slaves,
+            allow_extra: false,
+        }
+    }
+
+    /// Compare the layout with the slaves found on the bus
+    /// (ordered by ring position).
+    pub fn verify(&self, found: &[SlaveInfo]) -> BusReport {
+        let resolved: Vec<Option<usize>> = self
+            .slaves
+            .iter()
+            .map(|exp| resolve(exp.addr, found))
+            .collect();
+
+        let mut issues = vec![];
+        for (exp, pos) in self.slaves.iter().zip(&resolved) {
+            let pos = match *pos {
+                Some(pos) => pos,
+                None => {
+                    issues.push(BusIssue::Missing {
+                        addr: exp.addr,
+                        expected: exp.id,
+                    });
+                    continue;
+                }
+            };
+            let slave = &found[pos];
+            let position = slave.ring_pos;
+            if exp.id != slave.id {
+                // Is the expected device at another expected position, where
+                // this slave's device is expected?
+                let swapped = self.slaves.iter().zip(&resolved).find_map(|(other, p)| {
+                    let p = (*p)?;
+                    if p != pos && other.id == slave.id && found[p].id == exp.id {
+                        Some(found[p].ring_pos)
+                    } else {
+                        None
+                    }
+                });
+                issues.push(match swapped {
+                    Some(other_position) => BusIssue::Swapped {
+                        position,
+                        other_position,
+                        expected: exp.id,
+                        found: slave.id,
+                    },
+                    None => BusIssue::WrongDevice {
+                        position,
+                        expected: exp.id,
+                        found: slave.id,
+                    },
+                });
+                continue;
+            }
+            if let Some(rev) = exp.revision {
+                if rev != slave.rev.revision_number {
+                    issues.push(BusIssue::WrongRevision {
+                        position,
+                        expected: rev,
+                        found: slave.rev.revision_number,
+                    });
+                }
+            }
+            if let Some(serial) = exp.serial {
+                if serial != slave.rev.serial_number {
+                    issues.push(BusIssue::WrongSerial {
+                        position,
+                        expected: serial,
+                        found: slave.rev.serial_number,
+                    });
+                }
+            }
+        }
+
+        if !self.allow_extra {
+            for (pos, slave) in found.iter().enumerate() {
+                if !resolved.contains(&Some(pos)) {
+                    issues.push(BusIssue::Extra {
+                        position: slave.ring_pos,
+                        found: slave.id,
+                    });
+                }
+            }
+        }
+        BusReport { issues }
+    }
+}
+
+/// Index of the slave with the given address, following the addressing
+/// rules of the master (alias and offset from the aliased slave).
+fn resolve(addr: SlaveAddr, found: &[SlaveInfo]) -> Option<usize> {
+    let (alias, offset) = addr.as_pair();
+    let base = if alias == 0 {
+        0
+    } else {
+        found.iter().position(|s| s.alias == alias)?
+    };
+    let pos = base + usize::from(offset);
+    if pos < found.len() {
+        Some(pos)
+    } else {
+        None
+    }
+}
+
+#[test]
+fn test_verify() {
+    let slave = |pos: u16, alias, product_code, revision_number| SlaveInfo {
+        name: String::new(),
+        ring_pos: pos,
+        id: SlaveId::new(2, product_code),
+        rev: SlaveRev::new(revision_number, 0),
+        alias,
+        current_on_ebus: 0,
+        al_state: AlState::PreOp,
+        error_flag: 0,
+        sync_count: 0,
+        sdo_count: 0,
+        ports: Default::default(),
+        group: String::new(),
+        order: String::new(),
+        mailbox: SlaveMailboxInfo::default(),
+        coe: CoeDetails::default(),
+        has_general_category: false,
+        enable_safeop: false,
+        enable_not_lrw: false,
+        fmmu_bit: false,
+        dc: SlaveDcInfo {
+            supported: false,
+            range: DcRange::Bits32,
+            has_system_time: false,
+            transmission_delay: 0,
+        },
+        sii_size: 0,
+    };
+    let found = [
+        slave(0, 0, 0x1100, 1),
+        slave(1, 0, 0x2000, 1),
+        slave(2, 0, 0x1000, 2),
+        slave(3, 7, 0x3000, 1),
+        slave(4, 0, 0x4000, 1),
+    ];
+    let exp = |addr, product_code| ExpectedSlave::new(addr, SlaveId::new(2, product_code));
+    let mut layout = BusLayout::new(vec![
+        exp(SlaveAddr::ByPos(0), 0x1100).with_revision(1),
+        exp(SlaveAddr::ByPos(1), 0x1000),
+        exp(SlaveAddr::ByPos(2), 0x2000).with_revision(1),
+        exp(SlaveAddr::ByAlias(7, 0), 0x3000).with_revision(2),
+        exp(SlaveAddr::ByAlias(7, 3), 0x5000),
+    ]);
+    let report = layout.verify(&found);
+    assert_eq!(
+        report.issues,
+        vec![
+            BusIssue::Swapped {
+                position: 1,
+                other_position: 2,
+                expected: SlaveId::new(2, 0x1000),
+                found: SlaveId::new(2, 0x2000),
+            },
+            BusIssue::Swapped {
+                position: 2,
+                other_position: 1,
+                expected: SlaveId::new(2, 0x2000),
+                found: SlaveId::new(2, 0x1000),
+            },
+            BusIssue::WrongRevision {
+                position: 3,
+                expected: 2,
+                found: 1
+            },
+            BusIssue::Missing {
+                addr: SlaveAddr::ByAlias(7, 3),
+                expected: SlaveId::new(2, 0x5000),
+            },
+            BusIssue::Extra {
+                position: 4,
+                found: SlaveId::new(2, 0x4000),
+            },
+        ]
+    );
+    assert!(report.into_result().is_err());
+
+    layout.slaves.truncate(1);
+    layout.allow_extra = true;
+    assert!(layout.verify(&found).is_ok());
+}
@@ -0,0 +1,222 @@
+// Part of ethercat-rs. Copyright 2018-2020 by the authors.
+// This work is dual-licensed under Apache 2.0 and MIT terms.
+
+use crate::{master::Master, types::*};
+use std::{
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        mpsc, Arc,
+    },
+    time::Duration,
+};
+
+/// A change of the master, slave config or domain state.
+///
+/// The first poll reports the initial state of everything.
+#[derive(Debug, Clone, PartialEq)]
+pub enum StateEvent {
+    LinkUp,
+    LinkDown,
+    SlavesResponding {
+        count: u32,
+    },
+    /// The online, operational or AL state of a slave config changed.
+    SlaveConfig {
+        config: SlaveConfigIdx,
+        state: SlaveConfigState,
+    },
+    /// The working counter state of a domain changed.
+    WcState {
+        domain: DomainIdx,
+        wc_state: WcState,
+        working_counter: u32,
+    },
+}
+
+/// Detects state changes in the cyclic task and sends them to a
+/// [`StateEvents`] receiver.
+///
+/// Sending never blocks: if the receiver does not keep up, events are
+/// dropped and counted (see [`StateEvents::dropped`]).
+#[derive(Debug)]
+pub struct StateWatcher {
+    master: Option<MasterState>,
+    configs: Vec<(SlaveConfigIdx, Option<SlaveConfigState>)>,
+    domains: Vec<(DomainIdx, Option<WcState>)>,
+    tx: mpsc::SyncSender<StateEvent>,
+    dropped: Arc<AtomicU64>,
+}
+
+/// Receiving end of a [`StateWatcher`].
+#[derive(Debug)]
+pub struct StateEvents {
+    rx: mpsc::Receiver<StateEvent>,
+    dropped: Arc<AtomicU64>,
+}
+
+impl StateWatcher {
+    /// Create a watcher for the given slave configs and domains, with a
+    /// channel that buffers up to `capacity` events.
+    pub fn new(
+        configs: Vec<SlaveConfigIdx>,
+        domains: Vec<DomainIdx>,
+        capacity: usize,
+    ) -> (Self, StateEvents) {
+        let (tx, rx) = mpsc::sync_channel(capacity);
+        let dropped = Arc::new(AtomicU64::new(0));
+        let watcher = Self {
+            master: None,
+            configs: configs.into_iter().map(|c| (c, None)).collect(),
+            domains: domains.into_iter().map(|d| (d, None)).collect(),
+            tx,
+            dropped: dropped.clone(),
+        };
+        (watcher, StateEvents { rx, dropped })
+    }
+
+    /// Read all states and send the changes since the last poll.
+    pub fn poll(&mut self, master: &Master) -> Result<()> {
+        self.update_master(master.state()?);
+        for i in 0..self.configs.len() {
+            let state = master.slave_config(self.configs[i].0).state(master)?;
+            self.update_config(i, state);
+        }
+        for i in 0..self.domains.len() {
+            let state = master.domain(self.domains[i].0).state()?;
+            self.update_domain(i, &state);
+        }
+        Ok(())
+    }
+
+    fn update_master(&mut self, state: MasterState) {
+        let old = self.master.as_ref();
+        if old.map(|s| s.link_up) != Some(state.link_up) {
+            self.send(if state.link_up {
+                StateEvent::LinkUp
+            } else {
+                StateEvent::LinkDown
+            });
+        }
+        if old.map(|s| s.slaves_responding) != Some(state.slaves_responding) {
+            self.send(StateEvent::SlavesResponding {
+                count: state.slaves_responding,
+            });
+        }
+        self.master = Some(state);
+    }
+
+    fn update_config(&mut self, i: usize, state: SlaveConfigState) {
+        let (config, old) = &mut self.configs[i];
+        if *old != Some(state) {
+            *old = Some(state);
+            let config = *config;
+            self.send(StateEvent::SlaveConfig { config, state });
+        }
+    }
+
+    fn update_domain(&mut self, i: usize, state: &DomainState) {
+        let (domain, old) = &mut self.domains[i];
+        if *old != Some(state.wc_state) {
+            *old = Some(state.wc_state);
+            let domain = *domain;
+            self.send(StateEvent::WcState {
+                domain,
+                wc_state: state.wc_state,
+                working_counter: state.working_counter,
+            });
+        }
+    }
+
+    fn send(&self, event: StateEvent) {
+        if self.tx.try_send(event).is_err() {
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+impl StateEvents {
+    /// Wait for the next event (`None` once the watcher is dropped).
+    pub fn recv(&self) -> Option<StateEvent> {
+        self.rx.recv().ok()
+    }
+
+    pub fn recv_timeout(&self, timeout: Duration) -> Option<StateEvent> {
+        self.rx.recv_timeout(timeout).ok()
+    }
+
+    pub fn try_recv(&self) -> Option<StateEvent> {
+        self.rx.try_recv().ok()
+    }
+
+    /// Iterate over the events until the watcher is dropped.
+    pub fn iter(&self) -> mpsc::Iter<'_, StateEvent> {
+        self.rx.iter()
+    }
+
+    /// Number of events that were dropped because the channel was full.
+    pub fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+}
+
+#[test]
+fn test_state_watcher() {
+    let (mut watcher, events) = StateWatcher::new(vec![3], vec![DomainIdx::from(0)], 4);
+    let master = |link_up, slaves_responding| MasterState {
+        slaves_responding,
+        al_states: 8,
+        link_up,
+    };
+    let op = SlaveConfigState {
+        online: true,
+        operational: true,
+        al_state: Some(AlState::Op),
+    };
+    let domain = |wc_state, working_counter| DomainState {
+        working_counter,
+        wc_state,
+        redundancy_active: false,
+    };
+    watcher.update_master(master(true, 2));
+    watcher.update_config(0, op);
+    watcher.update_domain(0, &domain(WcState::Complete, 3));
+    let all: Vec<_> = std::iter::from_fn(|| events.try_recv()).collect();
+    assert_eq!(
+        all,
+        vec![
+            StateEvent::LinkUp,
+            StateEvent::SlavesResponding { count: 2 },
+            StateEvent::SlaveConfig {
+                config: 3,
+                state: op
+            },
+            StateEvent::WcState {
+                domain: DomainIdx::from(0),
+                wc_state: WcState::Complete,
+                working_counter: 3
+            },
+        ]
+    );
+
+    // unchanged states are not reported
+    watcher.update_master(master(true, 2));
+    watcher.update_config(0, op);
+    watcher.update_domain(0, &domain(WcState::Complete, 3));
+    assert_eq!(events.try_recv(), None);
+
+    watcher.update_master(master(false, 0));
+    watcher.update_domain(0, &domain(WcState::Zero, 0));
+    watcher.update_config(
+        0,
+        SlaveConfigState {
+            online: false,
+            operational: false,
+            al_state: None,
+        },
+    );
+    assert_eq!(events.try_recv(), Some(StateEvent::LinkDown));
+    // the channel holds 4 events
+    assert_eq!(events.dropped(), 0);
+    watcher.update_master(master(true, 2));
+    assert_eq!(events.dropped(), 1);
+}
@@ -0,0 +1,192 @@
+// Part of ethercat-rs. Copyright 2018-2020 by the authors.
+// This work is dual-licensed under Apache 2.0 and MIT terms.
+
+use crate::{master::Master, types::*};
+
+type Reaction = Box<dyn FnMut(&mut Master, &WkcEvent) -> Result<()> + Send>;
+
+/// A change of the working counter supervision.
+#[derive(Debug, Clone, PartialEq)]
+pub enum WkcEvent {
+    /// The working counter of a domain was not complete for the configured
+    /// number of consecutive cycles.
+    Fault {
+        domain: DomainIdx,
+        state: DomainState,
+        cycles: u32,
+    },
+    /// The working counters of all domains are complete again.
+    Recovered {
+        /// Number of cycles the fault lasted.
+        cycles: u64,
+    },
+}
+
+/// Supervises the working counters of the domains.
+///
+/// Call [`WkcSupervisor::check`] in every cycle after processing the
+/// domains. If the working counter of a domain is `Incomplete` or `Zero`
+/// for `debounce` consecutive cycles, the safe-state reaction is invoked
+/// once; when all working counters are `Complete` again, the recovery
+/// reaction is invoked.
+///
+/// While [`WkcSupervisor::is_faulted`] is set, the application should not
+/// write outputs besides the ones of the safe state.
+pub struct WkcSupervisor {
+    debounce: u32,
+    domains: Vec<(DomainIdx, u32)>,
+    faulted_cycles: Option<u64>,
+    on_fault: Option<Reaction>,
+    on_recovery: Option<Reaction>,
+}
+
+impl WkcSupervisor {
+    pub fn new(domains: Vec<DomainIdx>, debounce: u32) -> Self {
+        Self {
+            debounce: debounce.max(1),
+            domains: domains.into_iter().map(|d| (d, 0)).collect(),
+            faulted_cycles: None,
+            on_fault: None,
+            on_recovery: None,
+        }
+    }
+
+    /// Set the safe-state reaction, e.g. zero the outputs and disable drives.
+    pub fn with_safe_state<F>(mut self, f: F) -> Self
+    where
+        F: FnMut(&mut Master, &WkcEvent) -> Result<()> + Send + 'static,
+    {
+        self.on_fault = Some(Box::new(f));
+        self
+    }
+
+    /// Set the reaction when all working counters are complete again.
+    pub fn with_recovery<F>(mut self, f: F) -> Self
+    where
+        F: FnMut(&mut Master, &WkcEvent) -> Result<()> + Send + 'static,
+    {
+        self.on_recovery = Some(Box::new(f));
+        self
+    }
+
+    pub fn is_faulted(&self) -> bool {
+        self.faulted_cycles.is_some()
+    }
+
+    /// Read the domain states and run the reactions on a change.
+    ///
+    /// Does not allocate, so it can be called on the real-time thread.
+    pub fn check(&mut self, master: &mut Master) -> Result<Option<WkcEvent>> {
+        let event = self.evaluate(|idx| master.domain(idx).state())?;
+        if let Some(event) = &event {
+            if let Some(reaction) = self.reaction(event) {
+                reaction(master, event)?;
+            }
+        }
+        Ok(event)
+    }
+
+    /// Read the state of every domain with `read_state` and evaluate the cycle.
+    fn evaluate<F>(&mut self, mut read_state: F) -> Result<Option<WkcEvent>>
+    where
+        F: FnMut(DomainIdx) -> Result<DomainState>,
+    {
+        let mut fault = None;
+        for i in 0..self.domains.len() {
+            let state = read_state(self.domains[i].0)?;
+            let domain_fault = self.update_domain(i, state);
+            fault = fault.or(domain_fault);
+        }
+        Ok(self.finish_cycle(fault))
+    }
+
+    fn reaction(&mut self, event: &WkcEvent) -> Option<&mut Reaction> {
+        match event {
+            WkcEvent::Fault { .. } => self.on_fault.as_mut(),
+            WkcEvent::Recovered { .. } => self.on_recovery.as_mut(),
+        }
+    }
+
+    /// Record the state of the `i`-th domain (in the order given to `new`)
+    /// and return a fault if its debounce limit is reached.
+    fn update_domain(&mut self, i: usize, state: DomainState) -> Option<WkcEvent> {
+        let (domain, bad) = &mut self.domains[i];
+        if state.wc_state == WcState::Complete {
+            *bad = 0;
+            return None;
+        }
+        *bad = bad.saturating_add(1);
+        (*bad >= self.debounce).then_some(WkcEvent::Fault {
+            domain: *domain,
+            state,
+            cycles: *bad,
+        })
+    }
+
+    /// Evaluate the cycle after all domains were updated, with the first
+    /// fault that was found.
+    fn finish_cycle(&mut self, fault: Option<WkcEvent>) -> Option<WkcEvent> {
+        match (&mut self.faulted_cycles, fault) {
+            (None, Some(fault)) => {
+                self.faulted_cycles = Some(0);
+                Some(fault)
+            }
+            (Some(cycles), _) => {
+                *cycles += 1;
+                if self.domains.iter().all(|(_, bad)| *bad == 0) {
+                    let cycles = *cycles;
+                    self.faulted_cycles = None;
+                    Some(WkcEvent::Recovered { cycles })
+                } else {
+                    None
+                }
+            }
+            (None, None) => None,
+        }
+    }
+}
+
+#[test]
+fn test_wkc_supervisor() {
+    let state = |wc_state, working_counter| DomainState {
+        working_counter,
+        wc_state,
+        redundancy_active: false,
+    };
+    let complete = [state(WcState::Complete, 3), state(WcState::Complete, 2)];
+    let incomplete = [state(WcState::Complete, 3), state(WcState::Incomplete, 1)];
+    let cycle = |sup: &mut WkcSupervisor, states: &[DomainState]| {
+        sup.evaluate(|idx| Ok(states[usize::from(idx)].clone()))
+            .unwrap()
+    };
+    let mut sup = WkcSupervisor::new(vec![DomainIdx::from(0), DomainIdx::from(1)], 3)
+        .with_safe_state(|_, _| Ok(()));
+    assert_eq!(cycle(&mut sup, &complete), None);
+    assert_eq!(cycle(&mut sup, &incomplete), None);
+    assert_eq!(cycle(&mut sup, &incomplete), None);
+    // a single good cycle resets the debounce counter
+    assert_eq!(cycle(&mut sup, &complete), None);
+    assert_eq!(cycle(&mut sup, &incomplete), None);
+    assert_eq!(cycle(&mut sup, &incomplete), None);
+    let fault = cycle(&mut sup, &incomplete).unwrap();
+    assert_eq!(
+        fault,
+        WkcEvent::Fault {
+            domain: DomainIdx::from(1),
+            state: state(WcState::Incomplete, 1),
+            cycles: 3,
+        }
+    );
+    assert!(sup.is_faulted());
+    assert!(sup.reaction(&fault).is_some());
+    assert_eq!(cycle(&mut sup, &incomplete), None);
+    let recovered = cycle(&mut sup, &complete).unwrap();
+    assert_eq!(recovered, WkcEvent::Recovered { cycles: 2 });
+    assert!(!sup.is_faulted());
+    // no recovery reaction was set
+    assert!(sup.reaction(&recovered).is_none());
+
+    // errors reading a state are passed on
+    let err = sup.evaluate(|_| Err(Error::NotActivated));
+    assert!(matches!(err, Err(Error::NotActivated)));
+}